@@ -1,11 +1,26 @@
 //! The commands of this program.
 
+pub mod capture;
+pub mod check;
+pub mod completions;
+pub mod convert;
+pub mod current;
+pub mod doctor;
 pub mod edit;
+pub mod export_script;
 pub mod go;
+pub mod group;
+pub mod import;
 pub mod init;
 pub mod list_spaces;
 pub mod list_trees;
+pub mod man;
+pub mod migrate;
 pub mod new_tree;
+pub mod reload;
 pub mod remove_space;
 pub mod remove_tree;
+pub mod shell_init;
+pub mod status;
+pub mod stop;
 pub mod wdir;
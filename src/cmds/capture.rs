@@ -0,0 +1,136 @@
+//! The `capture` command.
+
+use std::{
+    collections::HashMap,
+    io::{Write, stdin, stdout},
+    path::Path,
+};
+
+use tmux_interface::{DisplayMessage, ListPanes, Tmux};
+
+use crate::{
+    Context, DsError, Result,
+    cmds::go::quote,
+    config::{SpaceTree, SpaceTreeId},
+    layout::Cell,
+    new_tree::yes_or_no,
+    utils,
+};
+
+/// A pane of the captured window.
+struct Pane {
+    /// The command running in the pane.
+    command: String,
+    /// The current directory of the pane.
+    path: String,
+}
+
+pub fn command(ctx: &mut Context, session: String, tree_name: String) -> Result {
+    let target = Context::session_target(&session);
+
+    // tmux replaces the tabs of the output, the paths are quoted instead.
+    let window = Tmux::with_command(
+        DisplayMessage::new()
+            .print()
+            .target_pane(&target)
+            .message("#{session_windows} #{window_layout} #{q:session_path}"),
+    )
+    .output()?;
+    if !window.success() {
+        return Err(DsError::SessionNotFound(session));
+    }
+    let window = String::from_utf8_lossy(&window.stdout()).trim().to_string();
+    let mut window = shlex::split(&window).unwrap_or_default().into_iter();
+    let (windows, layout, session_path) = (
+        window.next().unwrap_or_default(),
+        window.next().unwrap_or_default(),
+        window.next().unwrap_or_default(),
+    );
+
+    let panes = Tmux::with_command(
+        ListPanes::new()
+            .target(&target)
+            .format("#{pane_id} #{q:pane_current_command} #{q:pane_current_path}"),
+    )
+    .output()?;
+    if !panes.success() {
+        return Err(DsError::SessionNotFound(session));
+    }
+    let panes = String::from_utf8_lossy(&panes.stdout())
+        .lines()
+        .filter_map(|line| {
+            let mut fields = shlex::split(line)?.into_iter();
+            let id = fields.next()?.strip_prefix('%')?.parse::<usize>().ok()?;
+            let pane = Pane {
+                command: fields.next()?,
+                path: fields.next()?,
+            };
+            Some((id, pane))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let Some(cell) = Cell::parse(&layout) else {
+        return Err(DsError::CantCapture(
+            session,
+            format!("unknown layout {layout:?}"),
+        ));
+    };
+    let tree = cell
+        .into_tree(&mut |id| {
+            let pane = panes.get(&id)?;
+            pane_cmd(pane, &session_path).map(SpaceTree::Cmd)
+        })
+        .unwrap_or_else(|| SpaceTree::Cmd(String::from("clear")));
+
+    let mut stdout = stdout();
+    if windows != "1" {
+        writeln!(
+            stdout,
+            "The session has {windows} windows, only the current one is captured."
+        )?;
+    }
+    writeln!(stdout, "Captured tree:\n")?;
+    tree.pretty_print(&mut stdout, 0)?;
+    writeln!(stdout)?;
+
+    if ctx
+        .config
+        .get_tree(&SpaceTreeId::from(tree_name.as_str()))
+        .is_ok()
+    {
+        write!(
+            stdout,
+            "The tree {tree_name:?} already exists, replace it? "
+        )?;
+        stdout.flush()?;
+        if !yes_or_no(&mut stdout, &stdin(), false)? {
+            return Ok(());
+        }
+    }
+
+    ctx.config.insert_tree(tree_name.clone(), tree)?;
+    writeln!(stdout, "Saved as {tree_name:?}.")?;
+    Ok(())
+}
+
+/// The command reproducing the pane, going to its directory if it isn't the
+/// one of the session and running its command if it isn't a shell.
+fn pane_cmd(pane: &Pane, session_path: &str) -> Option<String> {
+    let mut cmds = Vec::new();
+
+    if pane.path != session_path {
+        let path = Path::new(&pane.path);
+        let dir = path
+            .strip_prefix(session_path)
+            .unwrap_or(path)
+            .to_string_lossy();
+        cmds.push(format!("cd {}", quote(&dir)));
+    }
+
+    if !utils::is_shell(&pane.command) {
+        cmds.push(pane.command.clone());
+    }
+
+    // the braces would be read as placeholders.
+    (!cmds.is_empty()).then(|| cmds.join(" && ").replace('{', "{{").replace('}', "}}"))
+}
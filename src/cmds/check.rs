@@ -0,0 +1,213 @@
+//! The `check` command.
+
+use std::{
+    env::{split_paths, var_os},
+    fmt::{self, Display},
+    path::Path,
+};
+
+use crate::{
+    Context, DsError, Result,
+    config::{CmdParsingError, PLACEHOLDERS, SpaceTree, SpaceTreeId, expand_placeholders},
+};
+
+/// Shell builtins and keywords, they aren't executables on the `PATH`.
+const BUILTINS: &[&str] = &[
+    ".", ":", "[", "alias", "bg", "builtin", "case", "cd", "command", "do", "done", "echo", "elif",
+    "else", "esac", "eval", "exec", "exit", "export", "false", "fg", "fi", "for", "if", "jobs",
+    "printf", "pwd", "read", "set", "shift", "source", "test", "then", "time", "trap", "true",
+    "type", "ulimit", "umask", "unalias", "unset", "until", "wait", "while",
+];
+
+/// How bad a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+/// Something wrong found in the config.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub level: Level,
+    /// The tree and the path inside it where it was found, if in a tree.
+    pub location: Option<(SpaceTreeId, String)>,
+    pub message: String,
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.level {
+            Level::Error => write!(f, "error: ")?,
+            Level::Warning => write!(f, "warning: ")?,
+        }
+        match &self.location {
+            Some((tree, path)) if path.is_empty() => write!(f, "tree {:?}: ", tree.0)?,
+            Some((tree, path)) => write!(f, "tree {:?} at {path}: ", tree.0)?,
+            None => {}
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Walks every tree of the config, and the trees referenced by the config and
+/// the Spaces, without launching anything.
+pub fn lint(ctx: &Context) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    let mut trees = ctx.config.trees.iter().collect::<Vec<_>>();
+    trees.sort_by(|a, b| a.0.0.cmp(&b.0.0));
+    for (id, tree) in trees {
+        tree.walk(&mut |path, tree| {
+            let mut found = |level, message| {
+                findings.push(Finding {
+                    level,
+                    location: Some((id.clone(), path.to_string())),
+                    message,
+                })
+            };
+            match tree {
+                SpaceTree::Cmd(cmd) => lint_cmd(cmd, &mut found),
+                SpaceTree::TmuxVSplit { lhs, rhs, size }
+                | SpaceTree::TmuxHSplit {
+                    top: lhs,
+                    bottom: rhs,
+                    size,
+                } => {
+                    if lhs.is_none() && rhs.is_none() {
+                        found(
+                            Level::Warning,
+                            String::from("the split has no tree on either side."),
+                        );
+                    }
+                    if let Some(size) = size
+                        && !(1..100).contains(size)
+                    {
+                        found(
+                            Level::Error,
+                            format!("the size {size}% isn't between 1% and 99%."),
+                        );
+                    }
+                }
+            }
+        });
+
+        let used = *id == ctx.config.default_tree || !ctx.db.spaces_using_tree(id).is_empty();
+        if !used {
+            findings.push(Finding {
+                level: Level::Warning,
+                location: Some((id.clone(), String::new())),
+                message: String::from("not used by any space nor as the default tree."),
+            });
+        }
+    }
+
+    if ctx.config.get_tree(&ctx.config.default_tree).is_err() {
+        findings.push(Finding {
+            level: Level::Error,
+            location: None,
+            message: format!(
+                "the default tree {:?} doesn't exist.",
+                ctx.config.default_tree.0
+            ),
+        });
+    }
+
+    let mut spaces = ctx.db.spaces_iter().collect::<Vec<_>>();
+    spaces.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, space) in spaces {
+        if ctx.config.get_tree(&space.tree).is_err() {
+            findings.push(Finding {
+                level: Level::Error,
+                location: None,
+                message: format!(
+                    "the space {name:?} uses the tree {:?} which doesn't exist.",
+                    space.tree.0
+                ),
+            });
+        }
+    }
+
+    findings
+}
+
+fn lint_cmd(cmd: &str, found: &mut impl FnMut(Level, String)) {
+    if cmd.trim().is_empty() {
+        found(Level::Error, String::from("the command is empty."));
+        return;
+    }
+
+    // the placeholders are replaced by their name, we only check the syntax.
+    let expanded = expand_placeholders(cmd, |key| {
+        PLACEHOLDERS.contains(&key).then(|| key.to_string())
+    });
+    let expanded = match expanded {
+        Ok(expanded) => expanded,
+        Err(err @ CmdParsingError::UnknownPlaceholder(_)) => {
+            found(
+                Level::Error,
+                format!("{err} Known ones are {}.", PLACEHOLDERS.join(", ")),
+            );
+            return;
+        }
+        Err(err) => {
+            found(Level::Error, err.to_string());
+            return;
+        }
+    };
+
+    for exe in executables(&expanded) {
+        if !on_path(&exe) {
+            found(
+                Level::Warning,
+                format!("the command {exe:?} wasn't found on the PATH."),
+            );
+        }
+    }
+}
+
+/// The executables run by a shell command line, e.g `clear` and `hx` in
+/// `clear && hx .`.
+fn executables(cmd: &str) -> Vec<String> {
+    cmd.split([';', '|', '&', '\n'])
+        .filter_map(|segment| {
+            let words = shlex::split(segment)?;
+            // skip the environment variables assignments.
+            words.into_iter().find(|word| !word.contains('='))
+        })
+        .filter(|exe| !BUILTINS.contains(&exe.as_str()))
+        .collect()
+}
+
+/// Can the executable be found, on the `PATH` or by its path?
+fn on_path(exe: &str) -> bool {
+    if exe.contains('/') {
+        return Path::new(exe).is_file();
+    }
+
+    var_os("PATH").is_some_and(|paths| split_paths(&paths).any(|dir| dir.join(exe).is_file()))
+}
+
+pub fn command(ctx: &Context, strict: bool) -> Result {
+    let findings = lint(ctx);
+
+    if findings.is_empty() {
+        println!("Everything looks good.");
+        return Ok(());
+    }
+
+    for finding in &findings {
+        println!("{finding}");
+    }
+
+    let errors = findings.iter().filter(|f| f.level == Level::Error).count();
+    let warnings = findings.len() - errors;
+    println!("\n{errors} error(s), {warnings} warning(s).");
+
+    let failing = if strict { findings.len() } else { errors };
+    if failing != 0 {
+        return Err(DsError::IssuesFound(failing));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! The `completions` command, and the dynamic completion of the names of the
+//! Spaces, Trees and groups.
+//!
+//! The script printed registers devspace itself as the completer, the shell
+//! calls it back with `COMPLETE=<shell>` on every completion so the names are
+//! read from the devspace dir when completing.
+
+use std::{
+    env::{args_os, current_exe},
+    io::stdout,
+    path::PathBuf,
+};
+
+use clap_complete::{CompletionCandidate, Shell, env::Shells};
+
+use crate::{Cli, Context, Result};
+
+/// The variable the shell sets when calling devspace to complete.
+pub(crate) const COMPLETE_VAR: &str = "COMPLETE";
+
+pub fn command(shell: Shell) -> Result {
+    let name = env!("CARGO_PKG_NAME");
+    let completer = current_exe()
+        .map(|exe| exe.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| name.to_string());
+
+    let shells = Shells::builtins();
+    let Some(env_completer) = shells.completer(&shell.to_string()) else {
+        unreachable!("clap_complete has a completer for every Shell");
+    };
+    env_completer.write_registration(COMPLETE_VAR, name, name, &completer, &mut stdout())?;
+    Ok(())
+}
+
+/// The names of the Spaces.
+pub fn spaces() -> Vec<CompletionCandidate> {
+    candidates(|ctx| {
+        ctx.db
+            .spaces_iter()
+            .map(|(name, space)| {
+                CompletionCandidate::new(name).help(space.description.clone().map(Into::into))
+            })
+            .collect()
+    })
+}
+
+/// The names of the Trees.
+pub fn trees() -> Vec<CompletionCandidate> {
+    candidates(|ctx| {
+        ctx.config
+            .trees
+            .keys()
+            .map(|name| CompletionCandidate::new(&name.0))
+            .collect()
+    })
+}
+
+/// The names of the groups, with their Spaces.
+pub fn groups() -> Vec<CompletionCandidate> {
+    candidates(|ctx| {
+        ctx.db
+            .groups_iter()
+            .map(|(name, spaces)| {
+                CompletionCandidate::new(name).help(Some(spaces.join(", ").into()))
+            })
+            .collect()
+    })
+}
+
+/// Loads the devspace dir of the line being completed, without writing
+/// anything, and gets the candidates from it.
+///
+/// Nothing is completed if the dir can't be loaded, errors would mess up the
+/// prompt.
+fn candidates(get: impl FnOnce(&Context) -> Vec<CompletionCandidate>) -> Vec<CompletionCandidate> {
+    let dir = match completed_dir() {
+        Some(dir) => dir,
+        None => Cli::default_dir(),
+    };
+    if !dir.is_dir() {
+        return Vec::new();
+    }
+
+    let Ok(mut ctx) = Context::load(dir) else {
+        return Vec::new();
+    };
+    ctx.set_read_only();
+    let mut candidates = get(&ctx);
+    // read only, terminating can't fail.
+    let _ = ctx.terminate();
+
+    candidates.sort_by(|a, b| a.get_value().cmp(b.get_value()));
+    candidates
+}
+
+/// The `--dir` given in the line being completed, the shell gives it after a
+/// `--`, e.g `devspace -- devspace --dir /tmp/ds go ""`.
+fn completed_dir() -> Option<PathBuf> {
+    let mut args = args_os().skip_while(|arg| arg != "--").skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--dir" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(dir) = arg.to_str().and_then(|arg| arg.strip_prefix("--dir=")) {
+            return Some(PathBuf::from(dir));
+        }
+    }
+    None
+}
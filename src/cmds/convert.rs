@@ -0,0 +1,15 @@
+//! The `convert` command.
+
+use crate::{Context, Result, format::Format};
+
+pub fn command(ctx: &mut Context, to: Format) -> Result {
+    let (db_path, conf_path) = ctx.file_paths();
+    println!(
+        "converting {} and {} to {to}.",
+        db_path.display(),
+        conf_path.display()
+    );
+
+    ctx.convert(to);
+    Ok(())
+}
@@ -0,0 +1,21 @@
+//! The `current` command.
+
+use std::env::current_dir;
+
+use crate::{Context, DsError, Result};
+
+pub fn command(ctx: &mut Context, wdir: bool) -> Result {
+    // run on every prompt, never rewrite the files.
+    ctx.set_read_only();
+
+    let Some((name, space)) = ctx.db.current_space()? else {
+        return Err(DsError::NotInSpace(current_dir()?));
+    };
+
+    if wdir {
+        println!("{}", space.wdir.to_string_lossy());
+    } else {
+        println!("{name}");
+    }
+    Ok(())
+}
@@ -0,0 +1,311 @@
+//! The `doctor` command.
+
+use std::{
+    collections::HashMap,
+    fs::{canonicalize, read_dir},
+    io::{Stdin, Write, stdin, stdout},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    Context, DsError, Result,
+    config::SpaceTreeId,
+    new_tree::{InteractiveError, yes_or_no},
+};
+
+/// How many directories above the missing working directory the relocation
+/// search starts.
+const RELOCATE_UP: usize = 2;
+
+/// How deep the relocation search goes.
+const RELOCATE_DEPTH: usize = 3;
+
+/// A problem found in the database.
+#[derive(Debug, Clone)]
+pub enum Issue {
+    /// The default tree of the config doesn't exist.
+    UnknownDefaultTree { tree: SpaceTreeId },
+    /// The working directory of the Space doesn't exist anymore.
+    MissingDir { space: String, wdir: PathBuf },
+    /// The Space uses a tree that isn't in the config.
+    UnknownTree { space: String, tree: SpaceTreeId },
+    /// Several Spaces have the same working directory.
+    DuplicateWdir { wdir: PathBuf, spaces: Vec<String> },
+}
+
+impl Issue {
+    pub fn describe(&self) -> String {
+        match self {
+            Issue::UnknownDefaultTree { tree } => {
+                format!("the default tree {:?} doesn't exist.", tree.0)
+            }
+            Issue::MissingDir { space, wdir } => {
+                format!(
+                    "the directory of {space:?}, {}, doesn't exist.",
+                    wdir.display()
+                )
+            }
+            Issue::UnknownTree { space, tree } => {
+                format!("the space {space:?} uses the unknown tree {:?}.", tree.0)
+            }
+            Issue::DuplicateWdir { wdir, spaces } => {
+                format!(
+                    "the spaces {} share the directory {}.",
+                    spaces.join(", "),
+                    wdir.display()
+                )
+            }
+        }
+    }
+}
+
+/// Scans the config for an unknown default tree, and the database for Spaces
+/// with a missing directory, an unknown tree or a directory shared with
+/// another Space.
+pub fn diagnose(ctx: &Context) -> Vec<Issue> {
+    let mut spaces = ctx.db.spaces_iter().collect::<Vec<_>>();
+    spaces.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut issues = Vec::new();
+    if ctx.config.get_tree(&ctx.config.default_tree).is_err() {
+        issues.push(Issue::UnknownDefaultTree {
+            tree: ctx.config.default_tree.clone(),
+        });
+    }
+    let mut wdirs: HashMap<PathBuf, Vec<String>> = HashMap::new();
+
+    for (name, space) in spaces {
+        if space.wdir.is_dir() {
+            let wdir = canonicalize(&space.wdir).unwrap_or_else(|_| space.wdir.clone());
+            wdirs.entry(wdir).or_default().push(name.clone());
+        } else {
+            issues.push(Issue::MissingDir {
+                space: name.clone(),
+                wdir: space.wdir.clone(),
+            });
+        }
+
+        if ctx.config.get_tree(&space.tree).is_err() {
+            issues.push(Issue::UnknownTree {
+                space: name.clone(),
+                tree: space.tree.clone(),
+            });
+        }
+    }
+
+    let mut duplicates = wdirs
+        .into_iter()
+        .filter(|(_, spaces)| spaces.len() > 1)
+        .map(|(wdir, spaces)| Issue::DuplicateWdir { wdir, spaces })
+        .collect::<Vec<_>>();
+    duplicates.sort_by_key(|issue| issue.describe());
+    issues.extend(duplicates);
+
+    issues
+}
+
+pub fn command(ctx: &mut Context, check: bool) -> Result {
+    let issues = diagnose(ctx);
+
+    if issues.is_empty() {
+        println!("No issue found.");
+        return Ok(());
+    }
+
+    if check {
+        for issue in &issues {
+            println!("- {}", issue.describe());
+        }
+        return Err(DsError::IssuesFound(issues.len()));
+    }
+
+    let mut stdout = stdout();
+    let stdin = stdin();
+
+    for issue in issues {
+        writeln!(stdout, "{}", issue.describe())?;
+        match issue {
+            Issue::UnknownDefaultTree { .. } => fix_default_tree(ctx, &mut stdout, &stdin)?,
+            Issue::MissingDir { space, wdir } => {
+                fix_missing_dir(ctx, &mut stdout, &stdin, space, &wdir)?
+            }
+            Issue::UnknownTree { space, .. } => fix_unknown_tree(ctx, &mut stdout, &stdin, space)?,
+            Issue::DuplicateWdir { spaces, .. } => {
+                fix_duplicate_wdir(ctx, &mut stdout, &stdin, &spaces)?
+            }
+        }
+        writeln!(stdout)?;
+    }
+
+    Ok(())
+}
+
+fn fix_missing_dir(
+    ctx: &mut Context,
+    o: &mut impl Write,
+    i: &Stdin,
+    space: String,
+    wdir: &Path,
+) -> Result {
+    // the Space may have been deleted while fixing a previous issue.
+    if ctx.db.get_space(&space).is_err() {
+        return Ok(());
+    }
+
+    let candidates = find_relocations(wdir);
+    let mut options = candidates
+        .iter()
+        .map(|c| format!("Relocate to {}", c.display()))
+        .collect::<Vec<_>>();
+    options.push(format!("Delete {space:?}"));
+    options.push(String::from("Skip"));
+
+    let choice = choose(o, i, &options)?;
+    if let Some(candidate) = candidates.get(choice) {
+        ctx.db.get_space_mut(&space)?.wdir = candidate.clone();
+    } else if choice == candidates.len() {
+        ctx.db.remove(&space);
+    }
+
+    Ok(())
+}
+
+fn fix_default_tree(ctx: &mut Context, o: &mut impl Write, i: &Stdin) -> Result {
+    let trees = tree_ids(ctx);
+    let mut options = trees
+        .iter()
+        .map(|tree| format!("Use {:?} as the default tree", tree.0))
+        .collect::<Vec<_>>();
+    options.push(String::from("Skip"));
+
+    if let Some(tree) = trees.get(choose(o, i, &options)?) {
+        ctx.config.default_tree = tree.clone();
+    }
+
+    Ok(())
+}
+
+fn fix_unknown_tree(ctx: &mut Context, o: &mut impl Write, i: &Stdin, space: String) -> Result {
+    if ctx.db.get_space(&space).is_err() {
+        return Ok(());
+    }
+
+    let trees = tree_ids(ctx);
+    let mut options = trees
+        .iter()
+        .map(|tree| format!("Use the tree {:?}", tree.0))
+        .collect::<Vec<_>>();
+    options.push(format!("Delete {space:?}"));
+    options.push(String::from("Skip"));
+
+    let choice = choose(o, i, &options)?;
+    if let Some(tree) = trees.get(choice) {
+        ctx.db.get_space_mut(&space)?.tree = tree.clone();
+    } else if choice == trees.len() {
+        ctx.db.remove(&space);
+    }
+
+    Ok(())
+}
+
+/// The ids of the trees in the config, the default one first.
+fn tree_ids(ctx: &Context) -> Vec<SpaceTreeId> {
+    let mut trees = ctx.config.trees.keys().cloned().collect::<Vec<_>>();
+    trees.sort_by_key(|tree| (*tree != ctx.config.default_tree, tree.0.clone()));
+    trees
+}
+
+fn fix_duplicate_wdir(
+    ctx: &mut Context,
+    o: &mut impl Write,
+    i: &Stdin,
+    spaces: &[String],
+) -> Result {
+    // keep the first one, it is the one the others are compared with.
+    for space in spaces.iter().skip(1) {
+        if ctx.db.get_space(space).is_err() {
+            continue;
+        }
+
+        write!(o, "Delete {space:?}? ")?;
+        if yes_or_no(o, i, false)? {
+            ctx.db.remove(space);
+        }
+    }
+
+    Ok(())
+}
+
+/// Asks to choose one of the options, returns its index.
+fn choose(o: &mut impl Write, i: &Stdin, options: &[String]) -> Result<usize> {
+    for (idx, option) in options.iter().enumerate() {
+        writeln!(o, "{}. {option}", idx + 1)?;
+    }
+    write!(o, ": ")?;
+    o.flush()?;
+
+    let mut buf = String::new();
+    i.read_line(&mut buf)?;
+    writeln!(o)?;
+
+    let int = buf
+        .trim()
+        .parse::<usize>()
+        .map_err(InteractiveError::InvalidInt)?;
+    if int == 0 || int > options.len() {
+        return Err(DsError::InteractiveError(InteractiveError::UnknownChoice(
+            int,
+            options.len(),
+        )));
+    }
+
+    Ok(int - 1)
+}
+
+/// Searches for git repositories with the same name as the missing `wdir`
+/// around where it was.
+fn find_relocations(wdir: &Path) -> Vec<PathBuf> {
+    let Some(name) = wdir.file_name() else {
+        return Vec::new();
+    };
+
+    // start from the nearest existing ancestor, then go up a bit.
+    let mut root = wdir.parent();
+    while let Some(dir) = root
+        && !dir.is_dir()
+    {
+        root = dir.parent();
+    }
+    for _ in 0..RELOCATE_UP {
+        root = root.map(|dir| dir.parent().unwrap_or(dir));
+    }
+    let Some(root) = root else {
+        return Vec::new();
+    };
+
+    let mut found = Vec::new();
+    let mut queue = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = queue.pop() {
+        if dir.file_name() == Some(name) && dir.join(".git").exists() {
+            found.push(dir.clone());
+        }
+        if depth >= RELOCATE_DEPTH {
+            continue;
+        }
+
+        let Ok(entries) = read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            // don't follow symlinks nor look inside hidden directories.
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if !hidden && entry.file_type().is_ok_and(|t| t.is_dir()) {
+                queue.push((path, depth + 1));
+            }
+        }
+    }
+
+    found.sort();
+    found
+}
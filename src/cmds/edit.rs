@@ -2,13 +2,50 @@
 
 use std::path::PathBuf;
 
-use crate::{Context, DsError, Result, config::SpaceTreeId};
+use clap::Args;
+
+use crate::{
+    Context, DsError, Result,
+    config::{Hook, Hooks, SpaceTreeId},
+};
+
+/// The hooks given to `edit`, an empty command removes the hook.
+#[derive(Args, Debug)]
+pub struct HookArgs {
+    /// Run by `go` before launching the Space.
+    #[arg(long, value_name = "CMD")]
+    pub on_start: Option<String>,
+    /// Run by `go` before attaching to the Space, launched or not, or opening
+    /// it in a new window.
+    #[arg(long, value_name = "CMD")]
+    pub on_attach: Option<String>,
+    /// Run by `go` once detached from the Space, outside of tmux only.
+    #[arg(long, value_name = "CMD")]
+    pub on_detach: Option<String>,
+    /// Run by `stop` and `restart` once the Space is stopped.
+    #[arg(long, value_name = "CMD")]
+    pub on_stop: Option<String>,
+}
+
+impl From<HookArgs> for Hooks {
+    fn from(args: HookArgs) -> Self {
+        Hooks {
+            on_start: args.on_start,
+            on_attach: args.on_attach,
+            on_detach: args.on_detach,
+            on_stop: args.on_stop,
+        }
+    }
+}
 
 pub fn command(
     ctx: &mut Context,
     space_name: String,
     wdir: Option<PathBuf>,
     tree: Option<SpaceTreeId>,
+    description: Option<String>,
+    tags: Vec<String>,
+    untags: Vec<String>,
 ) -> Result {
     let space = ctx.db.get_space_mut(&space_name)?;
     let old_space = space.clone();
@@ -27,19 +64,79 @@ pub fn command(
         space.tree = tree;
     }
 
+    if let Some(description) = description {
+        space.description = Some(description);
+    }
+
+    for tag in tags {
+        space.add_tag(tag);
+    }
+
+    for tag in &untags {
+        space.remove_tag(tag);
+    }
+
     println!(
-        "from {:?}, {}, {}",
+        "from {:?}, {}, {}, [{}]",
         space_name,
         old_space.wdir.display(),
-        old_space.tree.0
+        old_space.tree.0,
+        old_space.tags.join(", ")
     );
 
     println!(
-        "to   {:?}, {}, {}",
+        "to   {:?}, {}, {}, [{}]",
         space_name,
         space.wdir.display(),
-        space.tree.0
+        space.tree.0,
+        space.tags.join(", ")
     );
 
     Ok(())
 }
+
+/// Sets the hooks of the Space given, keeps the others.
+pub fn hooks(ctx: &mut Context, space_name: &str, hooks: Hooks) -> Result {
+    if hooks.is_empty() {
+        return Ok(());
+    }
+
+    let space = ctx.db.get_space_mut(space_name)?;
+    space.hooks.update(hooks);
+    for hook in Hook::ALL {
+        if let Some(cmd) = space.hooks.get(hook) {
+            println!("{}: {cmd}", hook.name());
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds and removes env files of the Space given.
+pub fn env_files(
+    ctx: &mut Context,
+    space_name: &str,
+    env_files: Vec<PathBuf>,
+    rm_env_files: Vec<PathBuf>,
+) -> Result {
+    if env_files.is_empty() && rm_env_files.is_empty() {
+        return Ok(());
+    }
+
+    let space = ctx.db.get_space_mut(space_name)?;
+    space.env_files.retain(|file| !rm_env_files.contains(file));
+    for file in env_files {
+        if !space.env_files.contains(&file) {
+            space.env_files.push(file);
+        }
+    }
+
+    let files = space
+        .env_files
+        .iter()
+        .map(|file| file.display().to_string())
+        .collect::<Vec<_>>();
+    println!("env files: [{}]", files.join(", "));
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+//! The `export-script` command.
+
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{
+    Context, Result,
+    cmds::go::{self, Launch},
+    config::cmd_placeholders,
+    format::Format,
+    layout::Cell,
+};
+
+/// What the Space is exported as.
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum ScriptFormat {
+    /// A bash script calling tmux.
+    #[default]
+    Bash,
+    /// A tmuxinator project file.
+    Tmuxinator,
+    /// A tmuxp session file.
+    Tmuxp,
+}
+
+/// Size of the window the layouts of the project files are computed for,
+/// tmux scales them to the real size of the window.
+const LAYOUT_SIZE: (usize, usize) = (200, 50);
+
+pub fn command(ctx: &mut Context, space_name: String, format: ScriptFormat) -> Result {
+    // the script goes to stdout, nothing is asked nor remembered.
+    ctx.set_read_only();
+    let session_name = ctx.session_name(&space_name);
+
+    if let ScriptFormat::Bash = format {
+        let cmds = go::session_commands(ctx, &space_name, &session_name, true, Launch::Export)?;
+        let target = go::quote(&Context::session_target(&session_name));
+
+        println!("#!/usr/bin/env bash");
+        println!("# Launches the Space {space_name:?}, exported from devspace.");
+        println!();
+        println!("if tmux has-session -t {target} 2>/dev/null; then");
+        println!("    exec tmux attach-session -t {target}");
+        println!("fi");
+        println!();
+        println!("exec {}", go::tmux_invocation(&cmds));
+        return Ok(());
+    }
+
+    let tree = go::space_tree(ctx, &space_name, Launch::Export)?;
+    let space = ctx.db.get_space(&space_name)?;
+
+    let (sx, sy) = LAYOUT_SIZE;
+    let cell = Cell::from_tree(Some(&tree), sx, sy, 0, 0);
+    let layout = cell.layout();
+    let panes = cell
+        .panes()
+        .into_iter()
+        .map(|cmd| cmd.map(|cmd| cmd_placeholders(cmd, space)).transpose())
+        .collect::<Result<Vec<_>>>()?;
+    let root = space.wdir.to_string_lossy().into_owned();
+
+    let file = match format {
+        ScriptFormat::Bash => unreachable!("handled above"),
+        ScriptFormat::Tmuxinator => Format::Yaml.serialize(&Tmuxinator {
+            name: session_name,
+            root,
+            windows: vec![BTreeMap::from([(
+                space_name,
+                TmuxinatorWindow { layout, panes },
+            )])],
+        })?,
+        ScriptFormat::Tmuxp => Format::Yaml.serialize(&Tmuxp {
+            session_name,
+            start_directory: root,
+            windows: vec![TmuxpWindow {
+                window_name: space_name,
+                layout,
+                panes: panes
+                    .into_iter()
+                    .map(|cmd| TmuxpPane {
+                        shell_command: cmd.into_iter().collect(),
+                    })
+                    .collect(),
+            }],
+        })?,
+    };
+    print!("{file}");
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Tmuxinator {
+    name: String,
+    root: String,
+    windows: Vec<BTreeMap<String, TmuxinatorWindow>>,
+}
+
+#[derive(Serialize)]
+struct TmuxinatorWindow {
+    layout: String,
+    /// The command of every pane, `None` for an empty pane.
+    panes: Vec<Option<String>>,
+}
+
+#[derive(Serialize)]
+struct Tmuxp {
+    session_name: String,
+    start_directory: String,
+    windows: Vec<TmuxpWindow>,
+}
+
+#[derive(Serialize)]
+struct TmuxpWindow {
+    window_name: String,
+    layout: String,
+    panes: Vec<TmuxpPane>,
+}
+
+#[derive(Serialize)]
+struct TmuxpPane {
+    shell_command: Vec<String>,
+}
@@ -1,47 +1,406 @@
 //! The `go` command.
 
-use tmux_interface::{AttachSession, HasSession, NewSession, StdIO, Tmux, TmuxCommands};
+use std::{collections::BTreeMap, env::var_os};
 
-use crate::{Context, Result};
+use tmux_interface::{
+    AttachSession, HasSession, NewSession, NewWindow, SelectWindow, StdIO, SwitchClient, Tmux,
+    TmuxCommand, TmuxCommands,
+};
+
+use crate::{
+    Context, DsError, Result,
+    cmds::reload,
+    config::{Hook, SpaceTree},
+    database::Space,
+    env_file, hooks, project,
+};
+
+/// Target of the window created by `--new-window` in the dry run scripts, and
+/// of the commands built before creating a window, its id is only known once
+/// created.
+const WINDOW_TARGET: &str = "$window";
+
+/// What the tmux commands of a Space are built for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Launch {
+    /// Sent to tmux.
+    Live,
+    /// Printed by `go --dry-run`.
+    DryRun,
+    /// Printed by `export-script`, for machines without devspace.
+    Export,
+}
+
+pub fn command(
+    ctx: &mut Context,
+    space_name: String,
+    dry_run: bool,
+    new_window: bool,
+    reload: bool,
+) -> Result {
+    // inside tmux attaching would nest the sessions, switch the client instead.
+    let inside_tmux = var_os("TMUX").is_some();
+    if dry_run {
+        // a dry run only shows what would be done, nothing is remembered.
+        ctx.set_read_only();
+    }
+    if new_window {
+        return go_window(ctx, space_name, dry_run, inside_tmux);
+    }
 
-pub fn command(ctx: &mut Context, space_name: String) -> Result {
     let session_name = ctx.session_name(&space_name);
-    let space = ctx.db.get_space(&space_name)?;
 
-    let session_exists = Tmux::with_command(HasSession::new().target_session(&session_name))
-        .output()?
-        .success();
+    // don't contact tmux at all, just show what would be sent.
+    if dry_run {
+        let mut cmds = session_commands(
+            ctx,
+            &space_name,
+            &session_name,
+            !inside_tmux,
+            Launch::DryRun,
+        )?;
+        if inside_tmux {
+            cmds.push(SwitchClient::new().target_session(Context::session_target(&session_name)));
+        }
+        print!("{}", tmux_script(&cmds));
+        return Ok(());
+    }
 
-    // the session already exists, don't create another one just attach to it.
+    let session_exists = Tmux::with_command(
+        HasSession::new().target_session(Context::session_target(&session_name)),
+    )
+    .output()?
+    .success();
+
+    // the session already exists, don't create another one just go to it.
     if session_exists {
-        let _ = Tmux::with_command(AttachSession::new().target_session(&session_name))
-            .stdin(Some(StdIO::Inherit))
-            .stdout(Some(StdIO::Inherit))
-            .stderr(Some(StdIO::Inherit))
-            .output()?;
+        if reload {
+            reload::command(ctx, &space_name, &session_name)?;
+        }
+
+        return attach(ctx, &[space_name], &session_name, inside_tmux, false);
+    }
+
+    // the hooks only run once the commands of the session are built.
+    let mut cmds = session_commands(ctx, &space_name, &session_name, !inside_tmux, Launch::Live)?;
+    if inside_tmux {
+        cmds.push(SwitchClient::new().target_session(Context::session_target(&session_name)));
+    }
+
+    hooks::run(ctx, &space_name, Hook::Start)?;
+    hooks::run(ctx, &space_name, Hook::Attach)?;
+    let output = Tmux::with_commands(cmds)
+        .stdin(Some(StdIO::Inherit))
+        .stdout(Some(StdIO::Inherit))
+        .stderr(Some(StdIO::Inherit))
+        .output()?;
+    if !output.success() {
+        return Err(DsError::CantGo(session_name));
+    }
+
+    // only a successful launch counts as a use.
+    ctx.db.get_space_mut(&space_name)?.mark_used(true);
+    ctx.db.set_last_space(&space_name);
+
+    // switching the client returns right away, only attaching waits for the
+    // client to detach.
+    if !inside_tmux {
+        hooks::run(ctx, &space_name, Hook::Detach)?;
+    }
+
+    Ok(())
+}
+
+/// Attaches to the existing session, or switches to it inside tmux, running
+/// the hooks of its Spaces.
+///
+/// The Spaces are marked as used once attached, and as launched if
+/// `launched`. The first one becomes the last used Space.
+fn attach(
+    ctx: &mut Context,
+    spaces: &[String],
+    session_name: &str,
+    inside_tmux: bool,
+    launched: bool,
+) -> Result {
+    for space_name in spaces {
+        hooks::run(ctx, space_name, Hook::Attach)?;
+    }
+
+    let cmd: TmuxCommand = if inside_tmux {
+        SwitchClient::new()
+            .target_session(Context::session_target(session_name))
+            .into()
+    } else {
+        AttachSession::new()
+            .target_session(Context::session_target(session_name))
+            .into()
+    };
+    let output = Tmux::with_command(cmd)
+        .stdin(Some(StdIO::Inherit))
+        .stdout(Some(StdIO::Inherit))
+        .stderr(Some(StdIO::Inherit))
+        .output()?;
+    if !output.success() {
+        return Err(DsError::CantGo(session_name.to_string()));
+    }
+
+    for space_name in spaces {
+        ctx.db.get_space_mut(space_name)?.mark_used(launched);
+    }
+    if let Some(first) = spaces.first() {
+        ctx.db.set_last_space(first);
+    }
+
+    if !inside_tmux {
+        for space_name in spaces {
+            hooks::run(ctx, space_name, Hook::Detach)?;
+        }
+    }
+    Ok(())
+}
 
+/// Opens the Space as a new window of the current tmux session.
+fn go_window(ctx: &mut Context, space_name: String, dry_run: bool, inside_tmux: bool) -> Result {
+    if !inside_tmux && !dry_run {
+        return Err(DsError::NotInTmux);
+    }
+
+    if dry_run {
+        let tree = space_tree(ctx, &space_name, Launch::DryRun)?;
+        let vars = env_file::redact(env_file::load(ctx, &space_name)?);
+        let space = ctx.db.get_space(&space_name)?;
+        let new_window = env_file::with_vars(new_window(&space_name, space, None), &vars);
+        let new_window = TmuxCommands::new().add_command(new_window);
+        let cmds = tree.build(space, WINDOW_TARGET, false)?;
+        println!("#!/bin/sh");
+        println!("window=$({})", tmux_invocation(&new_window));
+        println!("{}", tmux_invocation(&cmds));
         return Ok(());
     }
 
-    let mut cmds = TmuxCommands::new().add_command(
-        NewSession::new()
-            .attach()
+    open_window(ctx, &space_name, None, &[Hook::Start, Hook::Attach])?;
+
+    ctx.db.get_space_mut(&space_name)?.mark_used(true);
+    ctx.db.set_last_space(&space_name);
+
+    Ok(())
+}
+
+/// Goes to every Space of the group, the first one is attached and the others
+/// are launched detached.
+///
+/// If `combined`, the Spaces are the windows of a single session instead.
+pub fn group(ctx: &mut Context, group_name: String, combined: bool) -> Result {
+    let members = ctx.db.get_group(&group_name)?.to_vec();
+    let inside_tmux = var_os("TMUX").is_some();
+
+    if !combined {
+        // launch the others first, attaching to the first one blocks.
+        for member in members.iter().skip(1) {
+            let session_name = ctx.session_name(member);
+            let session_exists = Tmux::with_command(
+                HasSession::new().target_session(Context::session_target(&session_name)),
+            )
+            .output()?
+            .success();
+            if !session_exists {
+                let cmds = session_commands(ctx, member, &session_name, false, Launch::Live)?;
+                hooks::run(ctx, member, Hook::Start)?;
+                if !Tmux::with_commands(cmds).output()?.success() {
+                    return Err(DsError::CantGo(session_name));
+                }
+            }
+            ctx.db.get_space_mut(member)?.mark_used(!session_exists);
+        }
+
+        // a group always has at least one member.
+        return command(ctx, members[0].clone(), false, false, false);
+    }
+
+    let session_name = ctx.group_session_name(&group_name);
+    let session_exists = Tmux::with_command(
+        HasSession::new().target_session(Context::session_target(&session_name)),
+    )
+    .output()?
+    .success();
+
+    if !session_exists {
+        let first = &members[0];
+        let tree = space_tree(ctx, first, Launch::Live)?;
+        let vars = env_file::load(ctx, first)?;
+        let space = ctx.db.get_space(first)?;
+        let new_session = NewSession::new()
+            .detached()
             .session_name(&session_name)
-            .start_directory(space.wdir.to_string_lossy())
-            .into(),
-    );
+            .window_name(first)
+            .start_directory(space.wdir.to_string_lossy().into_owned());
+        let mut cmds =
+            TmuxCommands::new().add_command(env_file::with_vars(new_session.build(), &vars));
+        cmds.push_cmds(tree.build(space, &session_name, true)?);
+        hooks::run(ctx, first, Hook::Start)?;
+        if !Tmux::with_commands(cmds).output()?.success() {
+            return Err(DsError::CantGo(session_name));
+        }
 
-    let tree = ctx.config.get_tree(&space.tree)?;
-    let session_name = &ctx.session_name(&space_name);
-    let built_treee = tree.build(space, session_name)?;
+        for member in members.iter().skip(1) {
+            open_window(ctx, member, Some(&session_name), &[Hook::Start])?;
+        }
+        Tmux::with_command(
+            SelectWindow::new()
+                .target_window(format!("{}:^", Context::session_target(&session_name))),
+        )
+        .output()?;
+    }
 
-    cmds.push_cmds(built_treee);
+    attach(ctx, &members, &session_name, inside_tmux, !session_exists)
+}
+
+/// Opens the Space as a new window of the session, the current one if `None`,
+/// returns the id of the window.
+///
+/// The hooks `before` run once the commands of the window are built, before
+/// it is created.
+fn open_window(
+    ctx: &mut Context,
+    space_name: &str,
+    session: Option<&str>,
+    before: &[Hook],
+) -> Result<String> {
+    let tree = space_tree(ctx, space_name, Launch::Live)?;
+    let vars = env_file::load(ctx, space_name)?;
+    let space = ctx.db.get_space(space_name)?;
+    // the id of the window is only known once created, the commands are built
+    // a first time to fail before the hooks.
+    tree.build(space, WINDOW_TARGET, true)?;
+    for hook in before {
+        hooks::run(ctx, space_name, *hook)?;
+    }
 
+    let new_window = env_file::with_vars(new_window(space_name, space, session), &vars);
+    let output = Tmux::with_command(new_window).output()?;
+    if !output.success() {
+        // without its id the tree would be built in the current pane.
+        return Err(DsError::CantOpenWindow(
+            space_name.to_string(),
+            String::from_utf8_lossy(&output.stderr()).trim().to_string(),
+        ));
+    }
+    let window = String::from_utf8_lossy(&output.stdout()).trim().to_string();
+    let cmds = tree.build(space, &window, true)?;
     let _ = Tmux::with_commands(cmds)
         .stdin(Some(StdIO::Inherit))
         .stdout(Some(StdIO::Inherit))
         .stderr(Some(StdIO::Inherit))
         .output()?;
 
-    Ok(())
+    Ok(window)
+}
+
+/// The command creating the window of the Space, printing its id.
+fn new_window<'a>(space_name: &'a str, space: &Space, session: Option<&str>) -> TmuxCommand<'a> {
+    let new_window = NewWindow::new()
+        .print()
+        .format("#{window_id}")
+        .window_name(space_name)
+        .start_directory(space.wdir.to_string_lossy().into_owned());
+    match session {
+        // the window is added after the last one of the session.
+        Some(session) => new_window
+            .target_window(format!("{}:", Context::session_target(session)))
+            .build(),
+        None => new_window.build(),
+    }
+}
+
+/// The tree the Space is launched with, the one of the project file is
+/// preferred over the one of the Space, if trusted.
+///
+/// Only a live launch asks to trust the project file, the scripts printed
+/// use it only if it is already trusted.
+pub fn space_tree(ctx: &mut Context, space_name: &str, launch: Launch) -> Result<SpaceTree> {
+    let wdir = ctx.db.get_space(space_name)?.wdir.clone();
+    let project_tree = match launch {
+        Launch::Live => project::trusted_tree(&mut ctx.db, &wdir)?,
+        Launch::DryRun | Launch::Export => project::known_trusted_tree(&ctx.db, &wdir)?,
+    };
+    if let Some(tree) = project_tree {
+        return Ok(tree);
+    }
+
+    let space = ctx.db.get_space(space_name)?;
+    Ok(ctx.config.get_tree(&space.tree)?.clone())
+}
+
+/// Builds the tmux commands creating the session of the Space, with its tree,
+/// attaching to it or not.
+///
+/// Except for an export, the variables of the env files of the Space are set
+/// in the session, with their values hidden in a dry run. Only a live launch
+/// records the commands of the panes, the scripts printed stay plain tmux.
+pub fn session_commands<'a>(
+    ctx: &mut Context,
+    space_name: &str,
+    session_name: &'a str,
+    attach: bool,
+    launch: Launch,
+) -> Result<TmuxCommands<'a>> {
+    let tree = space_tree(ctx, space_name, launch)?;
+    let vars = match launch {
+        Launch::Live => env_file::load(ctx, space_name)?,
+        // the values may be secrets, only their keys are shown.
+        Launch::DryRun => env_file::redact(env_file::load(ctx, space_name)?),
+        Launch::Export => BTreeMap::new(),
+    };
+    let space = ctx.db.get_space(space_name)?;
+
+    let new_session = NewSession::new()
+        .session_name(session_name)
+        .start_directory(space.wdir.to_string_lossy().into_owned());
+    let new_session = if attach {
+        new_session.attach()
+    } else {
+        new_session.detached()
+    };
+    let mut cmds = TmuxCommands::new().add_command(env_file::with_vars(new_session.build(), &vars));
+
+    let built_treee = tree.build(space, session_name, launch == Launch::Live)?;
+    cmds.push_cmds(built_treee);
+
+    Ok(cmds)
+}
+
+/// Renders the tmux commands as a shell script running them in a single tmux
+/// invocation, like they are sent by devspace.
+pub fn tmux_script(cmds: &TmuxCommands) -> String {
+    format!("#!/bin/sh\n{}\n", tmux_invocation(cmds))
+}
+
+/// Renders the tmux commands as a single shell command line running them, one
+/// tmux command per line.
+pub fn tmux_invocation(cmds: &TmuxCommands) -> String {
+    let lines = cmds
+        .clone()
+        .into_cmds()
+        .iter()
+        .map(|cmd| {
+            cmd.to_vec()
+                .iter()
+                .map(|arg| match arg.as_ref() {
+                    WINDOW_TARGET => format!("\"{WINDOW_TARGET}\""),
+                    arg => quote(arg),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>();
+
+    format!("tmux {}", lines.join(" \\; \\\n    "))
+}
+
+/// Quotes the argument for a POSIX shell, if needed.
+pub fn quote(arg: &str) -> String {
+    shlex::try_quote(arg)
+        .map(|quoted| quoted.into_owned())
+        .unwrap_or_else(|_| arg.to_string())
 }
@@ -0,0 +1,77 @@
+//! The `group` command.
+
+use clap::Subcommand;
+use clap_complete::ArgValueCandidates;
+
+use crate::{Context, DsError, Result, cmds::completions};
+
+#[derive(Subcommand, Debug)]
+pub enum GroupCommand {
+    /// Creates a group of Spaces, launched together by `go --group`.
+    Create {
+        /// Name of the group.
+        name: String,
+        /// The Spaces of the group, the first one is the one gone to.
+        #[arg(required = true, add = ArgValueCandidates::new(completions::spaces))]
+        spaces: Vec<String>,
+    },
+    /// Removes the group with the given name, its Spaces are kept.
+    #[command(visible_alias = "rm")]
+    Remove {
+        /// Name of the group to remove.
+        #[arg(add = ArgValueCandidates::new(completions::groups))]
+        name: String,
+    },
+    /// Lists the groups with their Spaces.
+    ///
+    /// Returns a non-zero exit code if there is no groups stored.
+    #[command(visible_alias = "ls")]
+    List,
+}
+
+pub fn command(ctx: &mut Context, cmd: GroupCommand) -> Result {
+    match cmd {
+        GroupCommand::Create { name, spaces } => create(ctx, name, spaces),
+        GroupCommand::Remove { name } => {
+            ctx.db.get_group(&name)?;
+            ctx.db.remove_group(&name);
+            println!("Removed the group {name:?}.");
+            Ok(())
+        }
+        GroupCommand::List => list(ctx),
+    }
+}
+
+fn create(ctx: &mut Context, name: String, mut spaces: Vec<String>) -> Result {
+    if ctx.db.get_group(&name).is_ok() {
+        return Err(DsError::GroupAlreadyExists(name));
+    }
+    for space in &spaces {
+        ctx.db.get_space(space)?;
+    }
+
+    // keep the first occurrence of every Space, it sets the order of launch.
+    let mut seen = Vec::new();
+    spaces.retain(|space| {
+        let new = !seen.contains(space);
+        seen.push(space.clone());
+        new
+    });
+
+    println!("Created the group {name:?} with {}.", spaces.join(", "));
+    ctx.db.insert_group(name, spaces);
+    Ok(())
+}
+
+fn list(ctx: &Context) -> Result {
+    let mut groups = ctx.db.groups_iter().collect::<Vec<_>>();
+    if groups.is_empty() {
+        return Err(DsError::NothingToList);
+    }
+    groups.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (name, spaces) in groups {
+        println!("{name}: {}", spaces.join(", "));
+    }
+    Ok(())
+}
@@ -0,0 +1,522 @@
+//! The `import` command, converts tmuxinator and tmuxp project files to trees.
+
+use std::{
+    env::var,
+    fs::{canonicalize, read_to_string},
+    path::{Path, PathBuf},
+};
+
+use serde_yaml::Value;
+
+use crate::{
+    Context, DsError, Result,
+    cmds::go::quote,
+    config::{SpaceTree, SpaceTreeId},
+    database::Space,
+    format::Format,
+    layout::Cell,
+};
+
+/// Keys of a tmuxinator project that are converted.
+const TMUXINATOR_KEYS: &[&str] = &[
+    "name",
+    "project_name",
+    "root",
+    "project_root",
+    "pre_window",
+    "pre_tab",
+    "windows",
+    "tabs",
+];
+
+/// Keys of a tmuxinator window that are converted.
+const TMUXINATOR_WINDOW_KEYS: &[&str] = &["root", "layout", "panes", "pre"];
+
+/// Keys of a tmuxp session that are converted.
+const TMUXP_KEYS: &[&str] = &[
+    "session_name",
+    "start_directory",
+    "shell_command_before",
+    "windows",
+];
+
+/// Keys of a tmuxp window that are converted.
+const TMUXP_WINDOW_KEYS: &[&str] = &[
+    "window_name",
+    "start_directory",
+    "shell_command_before",
+    "layout",
+    "panes",
+];
+
+/// Keys of a tmuxp pane that are converted.
+const TMUXP_PANE_KEYS: &[&str] = &["shell_command"];
+
+/// A project read from a tmuxinator or tmuxp file.
+#[derive(Debug, Default)]
+struct Project {
+    name: String,
+    root: Option<String>,
+    windows: Vec<Window>,
+    /// What couldn't be converted.
+    unmapped: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct Window {
+    name: Option<String>,
+    layout: Option<String>,
+    /// The command of every pane, with the commands to run before, `None` for
+    /// an empty pane.
+    panes: Vec<Option<String>>,
+}
+
+pub fn command(ctx: &mut Context, files: Vec<PathBuf>, create_space: bool) -> Result {
+    for file in files {
+        let buf = read_to_string(&file)?;
+        let value: Value = Format::Yaml.parse_file(&file, &buf)?;
+
+        let mut project = if value.get("session_name").is_some() {
+            tmuxp(&value)
+        } else if value.get("name").is_some() || value.get("project_name").is_some() {
+            tmuxinator(&value)
+        } else {
+            return Err(DsError::CantImport(
+                file,
+                String::from("it is neither a tmuxinator nor a tmuxp file"),
+            ));
+        };
+        if project.windows.is_empty() {
+            return Err(DsError::CantImport(file, String::from("it has no window")));
+        }
+
+        println!("From {}:", file.display());
+        let mut first_tree = None;
+        let several = project.windows.len() > 1;
+        for (idx, window) in project.windows.iter().enumerate() {
+            let tree_name = if several {
+                let window_name = window.name.clone().unwrap_or_else(|| idx.to_string());
+                format!("{}-{window_name}", project.name)
+            } else {
+                project.name.clone()
+            };
+            let id = SpaceTreeId::from(tree_name.as_str());
+            first_tree.get_or_insert(id.clone());
+
+            if ctx.config.get_tree(&id).is_ok() {
+                println!("  the tree {tree_name:?} already exists, it is kept.");
+                continue;
+            }
+
+            let tree = window_tree(window, &mut project.unmapped);
+            ctx.config.insert_tree(tree_name.clone(), tree)?;
+            println!("  imported the tree {tree_name:?}.");
+        }
+
+        if create_space {
+            let tree = first_tree.expect("there is at least one window");
+            if several {
+                project.unmapped.push(format!(
+                    "a Space launches a single window, the Space uses the tree {:?}",
+                    tree.0
+                ));
+            }
+            import_space(ctx, &project, tree)?;
+        }
+
+        if !project.unmapped.is_empty() {
+            println!("  not imported:");
+            for unmapped in &project.unmapped {
+                println!("    - {unmapped}.");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates a Space for the root of the project.
+fn import_space(ctx: &mut Context, project: &Project, tree: SpaceTreeId) -> Result {
+    let Some(root) = &project.root else {
+        println!("  no root directory, no Space created.");
+        return Ok(());
+    };
+    let Ok(wdir) = canonicalize(expand_home(root)) else {
+        println!("  the root directory {root:?} doesn't exist, no Space created.");
+        return Ok(());
+    };
+    if ctx.db.get_space(&project.name).is_ok() {
+        println!("  the space {:?} already exists, it is kept.", project.name);
+        return Ok(());
+    }
+
+    println!(
+        "  created the space {:?} in {}.",
+        project.name,
+        wdir.display()
+    );
+    ctx.db.insert(project.name.clone(), Space::new(wdir, tree));
+    Ok(())
+}
+
+/// Converts the window in a tree, with its layout if there is one.
+fn window_tree(window: &Window, unmapped: &mut Vec<String>) -> SpaceTree {
+    let panes = window.panes.len();
+    if let Some(layout @ ("main-vertical" | "main-horizontal")) = window.layout.as_deref()
+        && panes > 1
+    {
+        unmapped.push(format!(
+            "the size of the main pane of the layout {layout:?} of the window {}, it takes half of the window",
+            window_name(&window.name)
+        ));
+    }
+    let cell = match &window.layout {
+        Some(layout) => Cell::named(layout, panes)
+            .or_else(|| Cell::parse(layout).filter(|cell| cell.panes().len() == panes))
+            .map(Cell::indexed)
+            .unwrap_or_else(|| {
+                unmapped.push(format!(
+                    "the layout {layout:?} of the window {}",
+                    window_name(&window.name)
+                ));
+                Cell::named("even-vertical", panes).expect("known layout")
+            }),
+        // without layout every pane is split below the previous one.
+        None => Cell::named("even-vertical", panes).expect("known layout"),
+    };
+
+    // a window without panes gets a single empty shell.
+    let tree =
+        cell.into_tree(&mut |idx| window.panes.get(idx).cloned().flatten().map(SpaceTree::Cmd));
+    tree.unwrap_or_else(|| SpaceTree::Cmd(String::from("clear")))
+}
+
+fn window_name(name: &Option<String>) -> String {
+    name.as_ref()
+        .map_or_else(|| String::from("without name"), |name| format!("{name:?}"))
+}
+
+/// Reads a tmuxinator project.
+fn tmuxinator(value: &Value) -> Project {
+    let mut project = Project {
+        name: string(value.get("name").or(value.get("project_name"))).unwrap_or_default(),
+        root: string(value.get("root").or(value.get("project_root"))),
+        ..Default::default()
+    };
+    unknown_keys(value, TMUXINATOR_KEYS, "", &mut project.unmapped);
+
+    let pre_window = commands(value.get("pre_window").or(value.get("pre_tab")));
+    let windows = value.get("windows").or(value.get("tabs"));
+    for window in windows.and_then(Value::as_sequence).into_iter().flatten() {
+        // every window is a map with a single key, its name.
+        let Some((name, window)) = window.as_mapping().and_then(|w| w.iter().next()) else {
+            continue;
+        };
+        let name = string(Some(name));
+        let context = format!(" of the window {}", window_name(&name));
+
+        let mut before = pre_window.clone();
+        let panes = match window {
+            Value::Mapping(_) => {
+                unknown_keys(
+                    window,
+                    TMUXINATOR_WINDOW_KEYS,
+                    &context,
+                    &mut project.unmapped,
+                );
+                if let Some(root) = string(window.get("root")) {
+                    before.insert(0, cd(&root));
+                }
+                before.extend(commands(window.get("pre")));
+
+                let panes = window.get("panes").and_then(Value::as_sequence);
+                match panes {
+                    Some(panes) => panes.iter().map(tmuxinator_pane).collect(),
+                    None => vec![None],
+                }
+            }
+            // a command, or several, run in a single pane.
+            pane => vec![tmuxinator_pane(pane)],
+        };
+
+        project.windows.push(Window {
+            name,
+            layout: string(window.get("layout")),
+            panes: panes
+                .into_iter()
+                .map(|pane| prepend(&before, pane))
+                .collect(),
+        });
+    }
+
+    project
+}
+
+/// A pane of tmuxinator, a command, a list of commands or a map of the title
+/// of the pane to its commands.
+fn tmuxinator_pane(pane: &Value) -> Option<String> {
+    match pane {
+        Value::Mapping(titled) => join(commands(titled.values().next())),
+        pane => join(commands(Some(pane))),
+    }
+}
+
+/// Reads a tmuxp session.
+fn tmuxp(value: &Value) -> Project {
+    let mut project = Project {
+        name: string(value.get("session_name")).unwrap_or_default(),
+        root: string(value.get("start_directory")),
+        ..Default::default()
+    };
+    unknown_keys(value, TMUXP_KEYS, "", &mut project.unmapped);
+
+    let session_before = commands(value.get("shell_command_before"));
+    let windows = value.get("windows").and_then(Value::as_sequence);
+    for window in windows.into_iter().flatten() {
+        let name = string(window.get("window_name"));
+        let context = format!(" of the window {}", window_name(&name));
+        unknown_keys(window, TMUXP_WINDOW_KEYS, &context, &mut project.unmapped);
+
+        let mut before = session_before.clone();
+        if let Some(dir) = string(window.get("start_directory")) {
+            before.insert(0, cd(&dir));
+        }
+        before.extend(commands(window.get("shell_command_before")));
+
+        let panes = window.get("panes").and_then(Value::as_sequence);
+        let panes = match panes {
+            Some(panes) => panes
+                .iter()
+                .map(|pane| {
+                    unknown_keys(pane, TMUXP_PANE_KEYS, &context, &mut project.unmapped);
+                    let pane = match pane {
+                        Value::Mapping(pane) => join(commands(pane.get("shell_command"))),
+                        Value::String(blank) if blank == "blank" || blank == "pane" => None,
+                        pane => join(commands(Some(pane))),
+                    };
+                    prepend(&before, pane)
+                })
+                .collect(),
+            None => vec![prepend(&before, None)],
+        };
+
+        project.windows.push(Window {
+            name,
+            layout: string(window.get("layout")),
+            panes,
+        });
+    }
+
+    project
+}
+
+/// Reports the keys of the map that aren't converted.
+fn unknown_keys(value: &Value, known: &[&str], context: &str, unmapped: &mut Vec<String>) {
+    let Some(map) = value.as_mapping() else {
+        return;
+    };
+    for key in map.keys().filter_map(Value::as_str) {
+        let message = format!("the key `{key}`{context}");
+        if !known.contains(&key) && !unmapped.contains(&message) {
+            unmapped.push(message);
+        }
+    }
+}
+
+fn string(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// A command or a list of commands.
+fn commands(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::Sequence(cmds)) => cmds.iter().filter_map(|cmd| string(Some(cmd))).collect(),
+        value => string(value).into_iter().collect(),
+    }
+}
+
+/// Joins commands to run them one after the other, `None` if there is none.
+fn join(cmds: Vec<String>) -> Option<String> {
+    (!cmds.is_empty()).then(|| cmds.join("; "))
+}
+
+/// Runs the commands before the ones of the pane, escaping the braces that
+/// would be read as placeholders.
+fn prepend(before: &[String], pane: Option<String>) -> Option<String> {
+    let cmds = before.iter().cloned().chain(pane).collect::<Vec<_>>();
+    join(cmds).map(|cmd| cmd.replace('{', "{{").replace('}', "}}"))
+}
+
+/// The command changing to the directory, the home isn't quoted to be
+/// expanded by the shell.
+fn cd(dir: &str) -> String {
+    match dir.strip_prefix("~/") {
+        Some(rest) => format!("cd ~/{}", quote(rest)),
+        None => format!("cd {}", quote(dir)),
+    }
+}
+
+/// Replaces the leading `~` of the path by the home directory.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), var("HOME")) {
+        (Some(rest), Ok(home)) => Path::new(&home).join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cmd(cmd: &str) -> Option<Box<SpaceTree>> {
+        Some(Box::new(SpaceTree::Cmd(String::from(cmd))))
+    }
+
+    fn yaml(buf: &str) -> Value {
+        serde_yaml::from_str(buf).expect("valid yaml")
+    }
+
+    #[test]
+    fn tmuxinator_project() {
+        let project = tmuxinator(&yaml(
+            "
+name: blog
+root: ~/blog
+pre_window: nvm use
+startup_window: editor
+windows:
+  - editor:
+      layout: main-vertical
+      panes:
+        - vim
+        - logs: tail -f log/{dev}.log
+        -
+  - server: bundle exec rails s
+",
+        ));
+        assert_eq!(project.name, "blog");
+        assert_eq!(project.root.as_deref(), Some("~/blog"));
+        assert_eq!(project.unmapped, vec!["the key `startup_window`"]);
+        assert_eq!(project.windows.len(), 2);
+
+        let editor = &project.windows[0];
+        assert_eq!(editor.name.as_deref(), Some("editor"));
+        assert_eq!(
+            editor.panes,
+            vec![
+                Some(String::from("nvm use; vim")),
+                Some(String::from("nvm use; tail -f log/{{dev}}.log")),
+                Some(String::from("nvm use")),
+            ]
+        );
+        assert_eq!(
+            project.windows[1].panes,
+            vec![Some(String::from("nvm use; bundle exec rails s"))]
+        );
+    }
+
+    #[test]
+    fn tmuxp_session() {
+        let project = tmuxp(&yaml(
+            "
+session_name: api
+start_directory: /srv/api
+windows:
+  - window_name: dev
+    start_directory: src
+    layout: even-horizontal
+    focus: true
+    panes:
+      - shell_command: [make, make test]
+      - blank
+",
+        ));
+        assert_eq!(project.name, "api");
+        assert_eq!(
+            project.unmapped,
+            vec!["the key `focus` of the window \"dev\""]
+        );
+
+        let dev = &project.windows[0];
+        assert_eq!(dev.layout.as_deref(), Some("even-horizontal"));
+        assert_eq!(
+            dev.panes,
+            vec![
+                Some(String::from("cd src; make; make test")),
+                Some(String::from("cd src")),
+            ]
+        );
+    }
+
+    #[test]
+    fn window_with_layout() {
+        let window = Window {
+            name: Some(String::from("dev")),
+            layout: Some(String::from("main-horizontal")),
+            panes: vec![Some(String::from("hx")), None, Some(String::from("htop"))],
+        };
+        let mut unmapped = Vec::new();
+        let tree = window_tree(&window, &mut unmapped);
+
+        // tmux sizes the main pane with `main-pane-height`, not in halves.
+        assert_eq!(unmapped.len(), 1, "{unmapped:?}");
+        assert_eq!(
+            tree,
+            SpaceTree::TmuxHSplit {
+                top: cmd("hx"),
+                bottom: Some(Box::new(SpaceTree::TmuxVSplit {
+                    lhs: None,
+                    rhs: cmd("htop"),
+                    size: None,
+                })),
+                size: None,
+            }
+        );
+    }
+
+    #[test]
+    fn window_with_unknown_layout() {
+        let window = Window {
+            name: None,
+            layout: Some(String::from("spiral")),
+            panes: vec![Some(String::from("a")), Some(String::from("b"))],
+        };
+        let mut unmapped = Vec::new();
+        let tree = window_tree(&window, &mut unmapped);
+
+        assert_eq!(
+            unmapped,
+            vec!["the layout \"spiral\" of the window without name"]
+        );
+        assert_eq!(
+            tree,
+            SpaceTree::TmuxHSplit {
+                top: cmd("a"),
+                bottom: cmd("b"),
+                size: None,
+            }
+        );
+    }
+
+    #[test]
+    fn window_without_panes() {
+        let project = tmuxinator(&yaml(
+            "
+name: empty
+windows:
+  - editor:
+      layout: tiled
+      panes: []
+",
+        ));
+        let mut unmapped = Vec::new();
+        let tree = window_tree(&project.windows[0], &mut unmapped);
+        assert_eq!(tree, SpaceTree::Cmd(String::from("clear")));
+        assert!(unmapped.is_empty());
+    }
+}
@@ -2,24 +2,42 @@
 
 use std::{fs::canonicalize, path::PathBuf};
 
-use crate::{Context, DsError, Result, config::SpaceTreeId, database::Space};
+use crate::{Context, DsError, Result, config::SpaceTreeId, database::Space, project};
 
-pub fn command(ctx: &mut Context, path: PathBuf, tree: Option<SpaceTreeId>) -> Result {
+pub fn command(
+    ctx: &mut Context,
+    path: PathBuf,
+    tree: Option<SpaceTreeId>,
+    description: Option<String>,
+    tags: Vec<String>,
+) -> Result {
     let abs = canonicalize(path)?;
 
     let dir_name = abs
         .file_name()
         .expect("the path of the directory can't finish with `..`")
-        .to_string_lossy();
+        .to_string_lossy()
+        .into_owned();
 
     if ctx.db.get_space(&dir_name).is_ok() {
-        return Err(DsError::SpaceAlreadyExists(dir_name.into_owned()));
+        return Err(DsError::SpaceAlreadyExists(dir_name));
     }
 
-    ctx.db.insert(
-        dir_name.into_owned(),
-        Space::new(abs, tree.unwrap_or(ctx.config.default_tree.clone())),
-    );
+    let tree = tree.unwrap_or(ctx.config.default_tree.clone());
+    ctx.config.get_tree(&tree)?;
+
+    // ask to trust the project file now rather than when going to the Space.
+    if project::trusted_tree(&mut ctx.db, &abs)?.is_some() {
+        println!("The tree of {} will be used.", project::PROJECT_FILE);
+    }
+
+    let mut space = Space::new(abs, tree);
+    space.description = description;
+    for tag in tags {
+        space.add_tag(tag);
+    }
+
+    ctx.db.insert(dir_name, space);
 
     Ok(())
 }
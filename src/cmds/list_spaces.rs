@@ -1,35 +1,82 @@
 //! The `list-spaces` command.
 
-use crate::{Context, DsError, Result};
+use std::cmp::Reverse;
 
-pub fn command(ctx: &Context) -> Result {
-    if ctx.db.is_empty() {
+use clap::ValueEnum;
+
+use crate::{Context, DsError, Result, database::Space, utils};
+
+/// How the Spaces are sorted when listed.
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum SpaceSort {
+    /// Alphabetically by name.
+    #[default]
+    Name,
+    /// Most recently used first.
+    Recent,
+    /// Most recently created first.
+    Created,
+    /// Most launched first.
+    Launches,
+}
+
+pub fn command(ctx: &Context, tags: Vec<String>, sort: SpaceSort) -> Result {
+    let mut spaces = ctx
+        .db
+        .spaces_iter()
+        .filter(|(_, space)| tags.iter().all(|tag| space.has_tag(tag)))
+        .collect::<Vec<_>>();
+
+    if spaces.is_empty() {
         return Err(DsError::NothingToList);
     }
 
-    let mut spaces = ctx.db.spaces_iter().collect::<Vec<_>>();
+    // always sort by name first so Spaces with the same key keep a stable order.
     spaces.sort_by(|a, b| a.0.cmp(b.0));
+    match sort {
+        SpaceSort::Name => {}
+        SpaceSort::Recent => spaces.sort_by_key(|(_, s)| Reverse(s.last_used_at)),
+        SpaceSort::Created => spaces.sort_by_key(|(_, s)| Reverse(s.created_at)),
+        SpaceSort::Launches => spaces.sort_by_key(|(_, s)| Reverse(s.launch_count)),
+    }
 
-    // can safely unwrap because we know there is at least one value.
-    let name_width = spaces.iter().map(|(s, _)| s.len()).max().unwrap().max(8);
-    let path_width = spaces
+    let rows = spaces
         .iter()
-        .map(|(_, s)| s.wdir.to_str().unwrap().len())
-        .max()
-        .unwrap();
-    let tree_width = spaces.iter().map(|(_, s)| s.tree.0.len()).max().unwrap();
+        .map(|(name, space)| row(name, space))
+        .collect::<Vec<_>>();
+
+    // can safely unwrap because we know there is at least one value.
+    let name_width = rows.iter().map(|r| r[0].len()).max().unwrap().max(8);
+    let path_width = rows.iter().map(|r| r[1].len()).max().unwrap();
+    let tree_width = rows.iter().map(|r| r[2].len()).max().unwrap();
+    let tags_width = rows.iter().map(|r| r[3].len()).max().unwrap().max(4);
+    let used_width = rows.iter().map(|r| r[4].len()).max().unwrap().max(9);
+    let launches_width = 8;
 
     println!(
-        "{:^name_width$}| {:^path_width$} | {:^tree_width$}",
-        "NAME", "PATH", "TREE"
+        "{:^name_width$}| {:^path_width$} | {:^tree_width$} | {:^tags_width$} | {:^used_width$} | {:^launches_width$} | DESCRIPTION",
+        "NAME", "PATH", "TREE", "TAGS", "LAST USED", "LAUNCHES"
     );
-    for (name, space) in spaces {
+    for [name, path, tree, tags, used, launches, description] in rows {
         println!(
-            "{:name_width$}| {:path_width$} | {:tree_width$}",
-            name,
-            space.wdir.to_string_lossy(),
-            space.tree.0
+            "{name:name_width$}| {path:path_width$} | {tree:tree_width$} | {tags:tags_width$} | {used:used_width$} | {launches:>launches_width$} | {description}"
         );
     }
     Ok(())
 }
+
+/// The columns of the Space in the list.
+fn row(name: &str, space: &Space) -> [String; 7] {
+    [
+        name.to_string(),
+        space.wdir.to_string_lossy().into_owned(),
+        space.tree.0.clone(),
+        space.tags.join(","),
+        space
+            .last_used_at
+            .map(utils::format_ago)
+            .unwrap_or_else(|| String::from("never")),
+        space.launch_count.to_string(),
+        space.description.clone().unwrap_or_default(),
+    ]
+}
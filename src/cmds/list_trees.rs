@@ -16,7 +16,10 @@ pub fn command(ctx: &Context) -> Result {
     writeln!(stdout, "List of trees:")?;
     for (name, tree) in trees {
         writeln!(stdout)?;
-        write!(stdout, "{:?}:\n  ", name.0)?;
+        match ctx.config.tree_source(name) {
+            Some(file) => write!(stdout, "{:?} (from {}):\n  ", name.0, file.display())?,
+            None => write!(stdout, "{:?}:\n  ", name.0)?,
+        }
         tree.pretty_print(&mut stdout, SpaceTree::PRINT_INDENT)?;
     }
 
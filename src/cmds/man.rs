@@ -0,0 +1,176 @@
+//! The `man` command, the man pages rendered from the definition of the
+//! command line.
+
+use std::{
+    fs::{File, create_dir_all},
+    io::{Write, stdout},
+    path::Path,
+};
+
+use clap::CommandFactory;
+use clap_mangen::Man;
+use roff::{Roff, bold, italic, roman};
+
+use crate::{Cli, DsError, Result};
+
+/// The Tree given as example in the page of devspace.
+const EXAMPLE_TREE: &str = "\
+TmuxVSplit(
+    lhs: Some(Cmd(\"hx {Space.wdir}\")),
+    rhs: Some(TmuxHSplit()),
+    size: Some(70),
+)";
+
+/// The syntax of the trees, in the page of devspace itself.
+fn trees_section(roff: &mut Roff) {
+    roff.control("SH", ["TREES"]);
+    roff.text([roman(
+        "A Tree is what a Space looks like once launched, written in RON in the \
+         `trees` map of the config, in the files of `trees.d/` or in the `include` \
+         files of the config. A Tree is one of:",
+    )]);
+
+    roff.control("TP", []);
+    roff.text([bold("Cmd"), roman("("), italic("COMMAND"), roman(")")]);
+    roff.text([roman(
+        "Runs the command in the pane. `{Space.wdir}` is replaced by the working \
+         directory of the Space, `{{` and `}}` give literal brackets.",
+    )]);
+
+    roff.control("TP", []);
+    roff.text([
+        bold("TmuxVSplit"),
+        roman("(lhs: Some("),
+        italic("TREE"),
+        roman("), rhs: Some("),
+        italic("TREE"),
+        roman("), size: Some("),
+        italic("PERCENT"),
+        roman("))"),
+    ]);
+    roff.text([roman(
+        "Splits the pane in two side by side, the left side gets `lhs` and the \
+         right side `rhs`. `size` is the percentage taken by the left side, the \
+         pane is split in halves without it.",
+    )]);
+
+    roff.control("TP", []);
+    roff.text([
+        bold("TmuxHSplit"),
+        roman("(top: Some("),
+        italic("TREE"),
+        roman("), bottom: Some("),
+        italic("TREE"),
+        roman("), size: Some("),
+        italic("PERCENT"),
+        roman("))"),
+    ]);
+    roff.text([roman(
+        "Splits the pane in two stacked, the top gets `top` and the bottom \
+         `bottom`. `size` is the percentage taken by the top, the pane is split in \
+         halves without it.",
+    )]);
+
+    roff.control("PP", []);
+    roff.text([roman(
+        "Every field of the splits is optional, a side without a Tree is an \
+         empty shell. For example, an editor on the left with two shells stacked \
+         on the right:",
+    )]);
+    roff.control("PP", []);
+    roff.control("nf", []);
+    roff.control("RS", ["4"]);
+    roff.text([roman(EXAMPLE_TREE)]);
+    roff.control("RE", []);
+    roff.control("fi", []);
+}
+
+/// The page of the command, subcommands don't have a version so the source
+/// is always set.
+fn page(cmd: &clap::Command) -> Man {
+    Man::new(cmd.clone()).source(concat!(
+        env!("CARGO_PKG_NAME"),
+        " ",
+        env!("CARGO_PKG_VERSION")
+    ))
+}
+
+/// Renders the page of the command, the page of devspace itself also gets the
+/// syntax of the trees.
+fn render(cmd: &clap::Command, w: &mut dyn Write) -> Result {
+    let man = page(cmd);
+    let is_root = cmd
+        .get_display_name()
+        .is_none_or(|name| name == cmd.get_name());
+    if !is_root {
+        man.render(w)?;
+        return Ok(());
+    }
+
+    man.render_title(w)?;
+    man.render_name_section(w)?;
+    man.render_synopsis_section(w)?;
+    man.render_description_section(w)?;
+    man.render_options_section(w)?;
+    man.render_subcommands_section(w)?;
+    let mut roff = Roff::new();
+    trees_section(&mut roff);
+    roff.to_writer(w)?;
+    man.render_version_section(w)?;
+    Ok(())
+}
+
+/// Writes the page of the command and of all its subcommands to the
+/// directory, returns how many were written.
+fn write_pages(cmd: &clap::Command, dir: &Path) -> Result<usize> {
+    let mut written = 0;
+    for sub in cmd.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        written += write_pages(sub, dir)?;
+    }
+
+    let path = dir.join(page(cmd).get_filename());
+    let mut file = File::create(path)?;
+    render(cmd, &mut file)?;
+    file.flush()?;
+    Ok(written + 1)
+}
+
+pub fn command(subcmds: Vec<String>, out_dir: Option<&Path>) -> Result {
+    let mut cli = Cli::command().disable_help_subcommand(true);
+    // sets the names of the pages of the subcommands, e.g `devspace-go`.
+    cli.build();
+
+    if let Some(dir) = out_dir {
+        create_dir_all(dir)?;
+        let written = write_pages(&cli, dir)?;
+        println!("Wrote {written} man pages to {}.", dir.display());
+        return Ok(());
+    }
+
+    let mut cmd = &cli;
+    for name in subcmds {
+        cmd = cmd
+            .find_subcommand(&name)
+            .ok_or(DsError::UnknownSubcommand(name))?;
+    }
+    render(cmd, &mut stdout())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SpaceTree;
+
+    #[test]
+    fn example_tree_parses() {
+        let tree: SpaceTree = ron::from_str(EXAMPLE_TREE).expect("valid tree");
+        assert!(matches!(
+            tree,
+            SpaceTree::TmuxVSplit {
+                lhs: Some(_),
+                rhs: Some(_),
+                size: Some(70),
+            }
+        ));
+    }
+}
@@ -0,0 +1,37 @@
+//! The `migrate` command.
+
+use crate::{Context, Result};
+
+pub fn command(ctx: &mut Context, check: bool) -> Result {
+    if check {
+        // nothing must be written, not even the backups.
+        ctx.set_read_only();
+    }
+
+    if ctx.migrations().is_empty() {
+        println!("Everything is up to date.");
+        return Ok(());
+    }
+
+    for migration in ctx.migrations() {
+        let verb = if check {
+            "would be migrated"
+        } else {
+            "migrated"
+        };
+        println!(
+            "{} {verb} from version {} to {}:",
+            migration.file.display(),
+            migration.from,
+            migration.to
+        );
+        for change in &migration.changes {
+            println!("  - {change}");
+        }
+        if !check {
+            println!("  backup at {}", migration.backup_path().display());
+        }
+    }
+
+    Ok(())
+}
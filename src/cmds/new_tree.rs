@@ -15,6 +15,8 @@ pub enum InteractiveError {
     InvalidInt(#[from] ParseIntError),
     #[error("you provided {0} but the number should've been between 1 and 3.")]
     UnknownTreeNumber(usize),
+    #[error("you provided {0} but the number should've been between 1 and {1}.")]
+    UnknownChoice(usize, usize),
 }
 
 pub fn command(ctx: &mut Context, tree_name: String) -> Result {
@@ -37,7 +39,7 @@ pub fn command(ctx: &mut Context, tree_name: String) -> Result {
     write!(stdout, "Add {tree_name:?} to the config? ")?;
     stdout.flush()?;
     if yes_or_no(&mut stdout, &stdin, false)? {
-        ctx.config.insert_tree(tree_name, tree);
+        ctx.config.insert_tree(tree_name, tree)?;
     }
     writeln!(stdout)?;
     Ok(())
@@ -55,7 +57,10 @@ pub fn new_base_tree(o: &mut impl Write, i: &Stdin) -> Result<SpaceTree> {
     i.read_line(&mut buf)?;
     writeln!(o)?;
 
-    let int = usize::from_str_radix(buf.trim(), 10).map_err(InteractiveError::InvalidInt)?;
+    let int = buf
+        .trim()
+        .parse::<usize>()
+        .map_err(InteractiveError::InvalidInt)?;
     let tree = match int {
         1 => new_cmd_tree(o, i)?,
         2 => new_tmux_vsplit_tree(o, i)?,
@@ -97,7 +102,11 @@ pub fn new_tmux_vsplit_tree(o: &mut impl Write, i: &Stdin) -> Result<SpaceTree>
         None
     };
 
-    Ok(SpaceTree::TmuxVSplit { lhs, rhs })
+    Ok(SpaceTree::TmuxVSplit {
+        lhs,
+        rhs,
+        size: None,
+    })
 }
 
 pub fn new_tmux_hsplit_tree(o: &mut impl Write, i: &Stdin) -> Result<SpaceTree> {
@@ -117,7 +126,11 @@ pub fn new_tmux_hsplit_tree(o: &mut impl Write, i: &Stdin) -> Result<SpaceTree>
         None
     };
 
-    Ok(SpaceTree::TmuxHSplit { top, bottom })
+    Ok(SpaceTree::TmuxHSplit {
+        top,
+        bottom,
+        size: None,
+    })
 }
 
 pub fn yes_or_no(o: &mut impl Write, i: &Stdin, default_yes: bool) -> Result<bool> {
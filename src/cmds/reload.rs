@@ -0,0 +1,518 @@
+//! Applies the tree of a Space to its running session, for `go --reload`.
+
+use std::collections::{HashMap, HashSet};
+
+use tmux_interface::{DisplayMessage, ListPanes, SendKeys, SplitWindow, Tmux};
+
+use crate::{
+    Context, DsError, Result,
+    cmds::go,
+    config::{PANE_CMD_OPTION, SpaceTree, cmd_placeholders, record_cmd, sized_split},
+    database::Space,
+    layout::{Cell, CellKind},
+    utils,
+};
+
+/// The panes of the running window, split in two like the trees are.
+enum Live {
+    /// A pane, with its id, the number of `%1`.
+    Pane(usize),
+    /// Panes side by side, when `row`, or stacked.
+    Split {
+        row: bool,
+        first: Box<Live>,
+        others: Box<Live>,
+    },
+}
+
+impl Live {
+    fn from_cell(cell: Cell<usize>) -> Self {
+        let (row, cells) = match cell.kind {
+            CellKind::Pane(id) => return Live::Pane(id),
+            CellKind::Row(cells) => (true, cells),
+            CellKind::Column(cells) => (false, cells),
+        };
+        Live::from_cells(row, cells)
+    }
+
+    fn from_cells(row: bool, mut cells: Vec<Cell<usize>>) -> Self {
+        let first = Live::from_cell(cells.remove(0));
+        let others = if cells.len() == 1 {
+            Live::from_cell(cells.remove(0))
+        } else {
+            Live::from_cells(row, cells)
+        };
+        Live::Split {
+            row,
+            first: Box::new(first),
+            others: Box::new(others),
+        }
+    }
+
+    fn panes(&self, panes: &mut Vec<usize>) {
+        match self {
+            Live::Pane(id) => panes.push(*id),
+            Live::Split { first, others, .. } => {
+                first.panes(panes);
+                others.panes(panes);
+            }
+        }
+    }
+}
+
+/// What the reload does to the panes of the window.
+trait Panes {
+    /// Splits the pane side by side, when `row`, or stacked, returns the id of
+    /// the new pane.
+    fn split(&mut self, id: usize, row: bool, size: Option<u8>) -> Result<usize>;
+    /// Sends `C-c` to the pane.
+    fn interrupt(&mut self, id: usize) -> Result;
+    /// Sends the command to the pane and runs it.
+    fn send(&mut self, id: usize, cmd: &str) -> Result;
+    /// Records the command sent to the pane in [`PANE_CMD_OPTION`].
+    fn record(&mut self, id: usize, cmd: &str) -> Result;
+}
+
+/// The panes of the running session of the Space.
+struct TmuxPanes<'a> {
+    space_name: &'a str,
+}
+
+impl Panes for TmuxPanes<'_> {
+    fn split(&mut self, id: usize, row: bool, size: Option<u8>) -> Result<usize> {
+        let target = format!("%{id}");
+        let split = if row {
+            SplitWindow::new().horizontal()
+        } else {
+            SplitWindow::new().vertical()
+        };
+        let split = split.print().format("#{pane_id}").target_pane(&target);
+        let output = Tmux::with_command(sized_split(split, size))
+            .output()?
+            .into_inner();
+        String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .strip_prefix('%')
+            .and_then(|id| id.parse::<usize>().ok())
+            .ok_or_else(|| {
+                DsError::CantReload(
+                    self.space_name.to_string(),
+                    String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                )
+            })
+    }
+
+    fn interrupt(&mut self, id: usize) -> Result {
+        let target = format!("%{id}");
+        Tmux::with_command(SendKeys::new().target_pane(&target).key("C-c")).output()?;
+        Ok(())
+    }
+
+    fn send(&mut self, id: usize, cmd: &str) -> Result {
+        let target = format!("%{id}");
+        Tmux::with_command(SendKeys::new().target_pane(&target).key(cmd)).output()?;
+        Tmux::with_command(SendKeys::new().target_pane(&target).key("C-m")).output()?;
+        Ok(())
+    }
+
+    fn record(&mut self, id: usize, cmd: &str) -> Result {
+        let target = format!("%{id}");
+        Tmux::with_command(record_cmd(&target, cmd.to_string())).output()?;
+        Ok(())
+    }
+}
+
+/// State of the reload, the panes of the window and what has been done.
+struct Reload<'a, P> {
+    panes: P,
+    space: &'a Space,
+    /// The command recorded in every pane, if any.
+    recorded: HashMap<usize, Option<String>>,
+    /// The panes running a shell, including the ones created by the reload.
+    idle: HashSet<usize>,
+    /// The panes created by the reload.
+    added: HashSet<usize>,
+    changes: Vec<String>,
+}
+
+/// Updates the first window of the running session of the Space to match its
+/// tree.
+///
+/// Missing panes are added and the panes whose command changed get `C-c` and
+/// the new command, the other panes are left untouched. Extra panes are kept
+/// and parts of the layout split in another direction are only reported,
+/// restarting the Space applies them.
+pub fn command(ctx: &mut Context, space_name: &str, session_name: &str) -> Result {
+    let tree = go::space_tree(ctx, space_name, go::Launch::Live)?;
+    let space = ctx.db.get_space(space_name)?;
+    let window = format!("{}:^", Context::session_target(session_name));
+
+    let layout = Tmux::with_command(
+        DisplayMessage::new()
+            .print()
+            .target_pane(&window)
+            .message("#{window_layout}"),
+    )
+    .output()?;
+    let layout = String::from_utf8_lossy(&layout.stdout()).trim().to_string();
+    let Some(cell) = Cell::parse(&layout) else {
+        return Err(DsError::CantReload(
+            space_name.to_string(),
+            format!("unknown layout {layout:?}"),
+        ));
+    };
+
+    // tmux replaces the tabs of the output, the commands are quoted instead.
+    let panes = Tmux::with_command(ListPanes::new().target(&window).format(format!(
+        "#{{pane_id}} #{{q:pane_current_command}} #{{q:{PANE_CMD_OPTION}}}"
+    )))
+    .output()?;
+    let mut recorded = HashMap::new();
+    let mut idle = HashSet::new();
+    for line in String::from_utf8_lossy(&panes.stdout()).lines() {
+        let mut fields = shlex::split(line).unwrap_or_default().into_iter();
+        let Some(id) = fields
+            .next()
+            .and_then(|id| id.strip_prefix('%')?.parse::<usize>().ok())
+        else {
+            continue;
+        };
+        if fields
+            .next()
+            .is_some_and(|command| utils::is_shell(&command))
+        {
+            idle.insert(id);
+        }
+        recorded.insert(id, fields.next().filter(|cmd| !cmd.is_empty()));
+    }
+
+    let mut reload = Reload {
+        panes: TmuxPanes { space_name },
+        space,
+        recorded,
+        idle,
+        added: HashSet::new(),
+        changes: Vec::new(),
+    };
+    reload.apply(Some(&tree), Live::from_cell(cell), "")?;
+
+    if reload.changes.is_empty() {
+        println!("The space {space_name:?} is up to date.");
+    }
+    for change in reload.changes {
+        println!("{change}");
+    }
+    Ok(())
+}
+
+impl<P: Panes> Reload<'_, P> {
+    fn apply(&mut self, tree: Option<&SpaceTree>, live: Live, path: &str) -> Result {
+        match (tree, live) {
+            (None, Live::Pane(_)) => {}
+            (Some(SpaceTree::Cmd(cmd)), Live::Pane(id)) => self.send(cmd, id, path)?,
+            (Some(SpaceTree::TmuxVSplit { lhs, rhs, size }), Live::Pane(id)) => {
+                let new_id = self.split(true, *size, id, &child_path(path, "rhs"))?;
+                self.apply(lhs.as_deref(), Live::Pane(id), &child_path(path, "lhs"))?;
+                self.apply(rhs.as_deref(), Live::Pane(new_id), &child_path(path, "rhs"))?;
+            }
+            (Some(SpaceTree::TmuxHSplit { top, bottom, size }), Live::Pane(id)) => {
+                let new_id = self.split(false, *size, id, &child_path(path, "bottom"))?;
+                self.apply(top.as_deref(), Live::Pane(id), &child_path(path, "top"))?;
+                let bottom_path = child_path(path, "bottom");
+                self.apply(bottom.as_deref(), Live::Pane(new_id), &bottom_path)?;
+            }
+            (
+                Some(SpaceTree::TmuxVSplit { lhs, rhs, .. }),
+                Live::Split {
+                    row: true,
+                    first,
+                    others,
+                },
+            ) => {
+                self.apply(lhs.as_deref(), *first, &child_path(path, "lhs"))?;
+                self.apply(rhs.as_deref(), *others, &child_path(path, "rhs"))?;
+            }
+            (
+                Some(SpaceTree::TmuxHSplit { top, bottom, .. }),
+                Live::Split {
+                    row: false,
+                    first,
+                    others,
+                },
+            ) => {
+                self.apply(top.as_deref(), *first, &child_path(path, "top"))?;
+                self.apply(bottom.as_deref(), *others, &child_path(path, "bottom"))?;
+            }
+            (None | Some(SpaceTree::Cmd(_)), Live::Split { first, others, .. }) => {
+                // the first pane takes the place of the tree, like `go` would.
+                self.apply(tree, *first, path)?;
+                let mut extra = Vec::new();
+                others.panes(&mut extra);
+                for id in extra {
+                    self.changes.push(format!(
+                        "The pane %{id} isn't in the tree, kept it, kill it with `tmux kill-pane -t %{id}`."
+                    ));
+                }
+            }
+            (Some(_), Live::Split { .. }) => {
+                self.changes.push(format!(
+                    "{} is split in another direction, restart the space to apply it.",
+                    capitalize(&pane_name(path))
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends the command to the pane if it isn't the one it was sent.
+    fn send(&mut self, cmd: &str, id: usize, path: &str) -> Result {
+        let cmd = cmd_placeholders(cmd, self.space)?;
+
+        let idle = self.idle.contains(&id);
+        let recorded = self.recorded.get(&id).cloned().flatten();
+        match &recorded {
+            Some(recorded) if *recorded == cmd => return Ok(()),
+            // launched before the commands were recorded, assume it matches.
+            None if !idle => return self.panes.record(id, &cmd),
+            _ => {}
+        }
+
+        if !idle {
+            self.panes.interrupt(id)?;
+        }
+        if recorded.is_some() {
+            self.changes
+                .push(format!("Re-sent the command of {}.", pane_name(path)));
+        } else if !self.added.contains(&id) {
+            self.changes
+                .push(format!("Sent the command of {}.", pane_name(path)));
+        }
+        self.panes.send(id, &cmd)?;
+        self.panes.record(id, &cmd)
+    }
+
+    /// Splits the pane, returns the id of the new one.
+    fn split(&mut self, row: bool, size: Option<u8>, id: usize, path: &str) -> Result<usize> {
+        let new_id = self.panes.split(id, row, size)?;
+
+        self.idle.insert(new_id);
+        self.added.insert(new_id);
+        self.changes.push(format!("Added {}.", pane_name(path)));
+        Ok(new_id)
+    }
+}
+
+fn child_path(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{path}.{name}")
+    }
+}
+
+fn pane_name(path: &str) -> String {
+    if path.is_empty() {
+        String::from("the window")
+    } else {
+        format!("the pane {path}")
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Panes logging what is done to them, new panes get the next free id.
+    #[derive(Default)]
+    struct FakePanes {
+        next_id: usize,
+        log: Vec<String>,
+    }
+
+    impl Panes for FakePanes {
+        fn split(&mut self, id: usize, row: bool, size: Option<u8>) -> Result<usize> {
+            let new_id = self.next_id;
+            self.next_id += 1;
+            let direction = if row { "row" } else { "column" };
+            self.log
+                .push(format!("split %{id} {direction} {size:?} -> %{new_id}"));
+            Ok(new_id)
+        }
+
+        fn interrupt(&mut self, id: usize) -> Result {
+            self.log.push(format!("interrupt %{id}"));
+            Ok(())
+        }
+
+        fn send(&mut self, id: usize, cmd: &str) -> Result {
+            self.log.push(format!("send %{id} {cmd}"));
+            Ok(())
+        }
+
+        fn record(&mut self, id: usize, cmd: &str) -> Result {
+            self.log.push(format!("record %{id} {cmd}"));
+            Ok(())
+        }
+    }
+
+    fn cmd(cmd: &str) -> Option<Box<SpaceTree>> {
+        Some(Box::new(SpaceTree::Cmd(String::from(cmd))))
+    }
+
+    fn space() -> Space {
+        Space::new("/srv/app".into(), "ide".into())
+    }
+
+    /// Reloads the window with the given layout, `recorded` gives the command
+    /// recorded in the panes and `busy` the panes not running a shell.
+    fn reload(
+        tree: &SpaceTree,
+        layout: &str,
+        recorded: &[(usize, &str)],
+        busy: &[usize],
+    ) -> (Vec<String>, Vec<String>) {
+        let space = space();
+        let cell = Cell::parse(layout).expect("valid layout");
+        let ids = cell.panes().into_iter().copied().collect::<Vec<_>>();
+        let mut reload = Reload {
+            panes: FakePanes {
+                next_id: ids.iter().max().map_or(0, |id| id + 1),
+                log: Vec::new(),
+            },
+            space: &space,
+            recorded: ids
+                .iter()
+                .map(|id| {
+                    let cmd = recorded.iter().find(|(pane, _)| pane == id);
+                    (*id, cmd.map(|(_, cmd)| cmd.to_string()))
+                })
+                .collect(),
+            idle: ids.into_iter().filter(|id| !busy.contains(id)).collect(),
+            added: HashSet::new(),
+            changes: Vec::new(),
+        };
+        reload
+            .apply(Some(tree), Live::from_cell(cell), "")
+            .expect("reload succeeds");
+        (reload.panes.log, reload.changes)
+    }
+
+    const TWO_PANES: &str = "0000,200x50,0,0{99x50,0,0,0,100x50,100,0,1}";
+
+    #[test]
+    fn up_to_date() {
+        let tree = SpaceTree::TmuxVSplit {
+            lhs: cmd("hx {Space.wdir}"),
+            rhs: None,
+            size: None,
+        };
+        let (log, changes) = reload(&tree, TWO_PANES, &[(0, "hx /srv/app")], &[0]);
+        assert!(log.is_empty());
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn changed_command_is_resent() {
+        let tree = SpaceTree::TmuxVSplit {
+            lhs: cmd("hx"),
+            rhs: cmd("cargo run"),
+            size: None,
+        };
+        let (log, changes) = reload(&tree, TWO_PANES, &[(0, "hx"), (1, "cargo test")], &[1]);
+        assert_eq!(
+            log,
+            vec!["interrupt %1", "send %1 cargo run", "record %1 cargo run"]
+        );
+        assert_eq!(changes, vec!["Re-sent the command of the pane rhs."]);
+    }
+
+    #[test]
+    fn unrecorded_busy_pane_is_assumed_up_to_date() {
+        let tree = SpaceTree::Cmd(String::from("htop"));
+        let (log, changes) = reload(&tree, "0000,200x50,0,0,3", &[], &[3]);
+        assert_eq!(log, vec!["record %3 htop"]);
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn missing_panes_are_added() {
+        let tree = SpaceTree::TmuxVSplit {
+            lhs: cmd("hx"),
+            rhs: Some(Box::new(SpaceTree::TmuxHSplit {
+                top: cmd("cargo run"),
+                bottom: None,
+                size: Some(70),
+            })),
+            size: None,
+        };
+        let (log, changes) = reload(&tree, TWO_PANES, &[(0, "hx")], &[0]);
+        assert_eq!(
+            log,
+            vec![
+                "split %1 column Some(70) -> %2",
+                "send %1 cargo run",
+                "record %1 cargo run",
+            ]
+        );
+        assert_eq!(
+            changes,
+            vec![
+                "Added the pane rhs.bottom.",
+                "Sent the command of the pane rhs.top.",
+            ]
+        );
+    }
+
+    #[test]
+    fn new_pane_gets_its_command_silently() {
+        let tree = SpaceTree::TmuxHSplit {
+            top: None,
+            bottom: cmd("make"),
+            size: None,
+        };
+        let (log, changes) = reload(&tree, "0000,200x50,0,0,0", &[], &[]);
+        assert_eq!(
+            log,
+            vec![
+                "split %0 column None -> %1",
+                "send %1 make",
+                "record %1 make"
+            ]
+        );
+        assert_eq!(changes, vec!["Added the pane bottom."]);
+    }
+
+    #[test]
+    fn extra_panes_are_kept() {
+        let tree = SpaceTree::Cmd(String::from("hx"));
+        let (log, changes) = reload(&tree, TWO_PANES, &[(0, "hx")], &[]);
+        assert!(log.is_empty());
+        assert_eq!(
+            changes,
+            vec!["The pane %1 isn't in the tree, kept it, kill it with `tmux kill-pane -t %1`."]
+        );
+    }
+
+    #[test]
+    fn other_direction_is_reported() {
+        let tree = SpaceTree::TmuxHSplit {
+            top: cmd("hx"),
+            bottom: None,
+            size: None,
+        };
+        let (log, changes) = reload(&tree, TWO_PANES, &[(0, "hx")], &[]);
+        assert!(log.is_empty());
+        assert_eq!(
+            changes,
+            vec!["The window is split in another direction, restart the space to apply it."]
+        );
+    }
+}
@@ -1,10 +1,22 @@
 //! The `remove` command.
 
-use crate::{Context, DsError, Result};
+use std::io::{Write, stdin, stdout};
 
-pub fn command(ctx: &mut Context, space: String) -> Result {
-    if ctx.db.get_space(&space).is_err() {
-        return Err(DsError::SpaceAlreadyExists(space));
+use crate::{Context, Result, new_tree::yes_or_no};
+
+/// Removes the Space, if `guessed` the name wasn't given and the removal is
+/// confirmed first.
+pub fn command(ctx: &mut Context, space: String, guessed: bool) -> Result {
+    let wdir = ctx.db.get_space(&space)?.wdir.clone();
+
+    if guessed {
+        let mut stdout = stdout();
+        write!(stdout, "Remove the space {space:?} of {}? ", wdir.display())?;
+        stdout.flush()?;
+        if !yes_or_no(&mut stdout, &stdin(), false)? {
+            writeln!(stdout, "Nothing removed.")?;
+            return Ok(());
+        }
     }
 
     ctx.db.remove(&space);
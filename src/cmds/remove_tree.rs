@@ -1,11 +1,56 @@
 //! The `remove-tree` command.
 
-use crate::{Context, Result, config::SpaceTreeId};
+use crate::{Context, DsError, Result, config::SpaceTreeId};
+
+pub fn command(
+    ctx: &mut Context,
+    tree_name: String,
+    reassign: Option<SpaceTreeId>,
+    force: bool,
+) -> Result {
+    let tree = SpaceTreeId(tree_name.clone());
 
-pub fn command(ctx: &mut Context, tree_name: String) -> Result {
     // because we propagate the error the check is still performed.
-    ctx.config.get_tree(&SpaceTreeId(tree_name.clone()))?;
+    ctx.config.get_tree(&tree)?;
+
+    let users = ctx
+        .db
+        .spaces_using_tree(&tree)
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>();
+    let is_default = ctx.config.default_tree == tree;
+
+    // without a default tree every command would fail, even when forced.
+    if is_default && reassign.is_none() {
+        return Err(DsError::DefaultTreeInUse(tree_name));
+    }
+
+    if let Some(reassign) = &reassign {
+        ctx.config.get_tree(reassign)?;
+        if *reassign == tree {
+            return Err(DsError::TreeInUse(
+                tree_name,
+                String::from("the tree it is reassigned to"),
+            ));
+        }
+    } else if !force && !users.is_empty() {
+        return Err(DsError::TreeInUse(
+            tree_name,
+            format!("the spaces {}", users.join(", ")),
+        ));
+    }
+
+    ctx.config.remove_tree(tree_name)?;
+
+    if let Some(reassign) = reassign {
+        for space in &users {
+            ctx.db.get_space_mut(space)?.tree = reassign.clone();
+        }
+        if is_default {
+            ctx.config.default_tree = reassign;
+        }
+    }
 
-    ctx.config.remove_tree(tree_name);
     Ok(())
 }
@@ -0,0 +1,72 @@
+//! The `shell-init` command.
+
+use clap::ValueEnum;
+
+use crate::Result;
+
+/// The shell the integration is written for.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// The integration for bash and zsh, `{cmd}` is the name of the wrapper.
+const POSIX_INIT: &str = r#"# devspace shell integration.
+#
+# `{cmd} cd SPACE` goes to the working directory of the Space, `{cmd}` alone
+# to the one of the Space containing the current directory, then of the last
+# used Space. Any other arguments are given to devspace.
+{cmd}() {
+    local dir
+    if [ "$#" -eq 0 ]; then
+        dir="$(command devspace wdir)" && cd -- "$dir"
+    elif [ "$1" = cd ]; then
+        shift
+        dir="$(command devspace wdir "$@")" && cd -- "$dir"
+    else
+        command devspace "$@"
+    fi
+}
+
+# Prints the name of the Space containing the current directory, nothing
+# outside of a Space, e.g `PS1='$(devspace_prompt) \w \$ '`, zsh needs
+# `setopt prompt_subst`.
+devspace_prompt() {
+    command devspace current 2>/dev/null
+}
+"#;
+
+/// The integration for fish, `{cmd}` is the name of the wrapper.
+const FISH_INIT: &str = r#"# devspace shell integration.
+#
+# `{cmd} cd SPACE` goes to the working directory of the Space, `{cmd}` alone
+# to the one of the Space containing the current directory, then of the last
+# used Space. Any other arguments are given to devspace.
+function {cmd}
+    if test (count $argv) -eq 0
+        set -l dir (command devspace wdir); and cd -- $dir
+    else if test "$argv[1]" = cd
+        set -l dir (command devspace wdir $argv[2..-1]); and cd -- $dir
+    else
+        command devspace $argv
+    end
+end
+
+# Prints the name of the Space containing the current directory, nothing
+# outside of a Space, e.g in `fish_prompt`.
+function devspace_prompt
+    command devspace current 2>/dev/null
+end
+"#;
+
+pub fn command(shell: Shell, cmd: &str) -> Result {
+    let init = match shell {
+        Shell::Bash | Shell::Zsh => POSIX_INIT,
+        Shell::Fish => FISH_INIT,
+    };
+
+    print!("{}", init.replace("{cmd}", cmd));
+    Ok(())
+}
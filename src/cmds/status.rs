@@ -0,0 +1,206 @@
+//! The `status` command.
+
+use std::collections::HashMap;
+
+use tmux_interface::{ListPanes, ListSessions, ListWindows, Tmux};
+
+use crate::{Context, DsError, Result, utils};
+
+/// A running tmux session.
+#[derive(Debug, Default)]
+pub struct Session {
+    /// Number of clients attached to it.
+    pub clients: usize,
+    pub windows: usize,
+    /// Names of its windows, the windows of a combined session are named
+    /// after their Space.
+    pub window_names: Vec<String>,
+    pub panes: usize,
+    /// When it was created, in seconds since the Unix epoch.
+    pub created_at: u64,
+}
+
+pub fn command(ctx: &Context, running_only: bool) -> Result {
+    let mut sessions = sessions()?;
+
+    let mut spaces = ctx
+        .db
+        .spaces_iter()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+    spaces.sort();
+
+    let mut groups = ctx
+        .db
+        .groups_iter()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+    groups.sort();
+
+    let mut rows = Vec::new();
+    for name in spaces {
+        // a Space can also run as a window of the combined session of a group.
+        let grouped = ctx.combined_session_names(name).iter().any(|sname| {
+            sessions
+                .get(sname)
+                .is_some_and(|session| session.window_names.contains(name))
+        });
+        match sessions.remove(&ctx.session_name(name)) {
+            Some(session) => rows.push(row(name, "running", Some(&session))),
+            None if grouped => rows.push(row(name, "grouped", None)),
+            None if !running_only => rows.push(row(name, "stopped", None)),
+            None => {}
+        }
+    }
+
+    let group_rows = groups
+        .into_iter()
+        .filter_map(|name| {
+            let session = sessions.remove(&ctx.group_session_name(name))?;
+            Some(row(name, "running", Some(&session)))
+        })
+        .collect::<Vec<_>>();
+
+    // the sessions left that look like a Space or a group one are orphans.
+    let prefixes = [ctx.session_name(""), ctx.group_session_name("")];
+    let mut orphans = sessions
+        .into_iter()
+        .filter(|(name, _)| {
+            prefixes
+                .iter()
+                .any(|prefix| name.len() > prefix.len() && name.starts_with(prefix))
+        })
+        .collect::<Vec<_>>();
+    orphans.sort_by(|a, b| a.0.cmp(&b.0));
+
+    if rows.is_empty() && group_rows.is_empty() && orphans.is_empty() {
+        return Err(DsError::NothingToList);
+    }
+
+    if !rows.is_empty() {
+        print_table(&rows);
+    }
+
+    if !group_rows.is_empty() {
+        if !rows.is_empty() {
+            println!();
+        }
+        println!("Combined sessions of groups, see `go --group --combined`:");
+        print_table(&group_rows);
+    }
+
+    if !orphans.is_empty() {
+        if !rows.is_empty() || !group_rows.is_empty() {
+            println!();
+        }
+        println!(
+            "Sessions of removed spaces or groups, kill them with `tmux kill-session -t <SESSION>`:"
+        );
+        let rows = orphans
+            .iter()
+            .map(|(name, session)| row(name, "orphan", Some(session)))
+            .collect::<Vec<_>>();
+        print_table(&rows);
+    }
+
+    Ok(())
+}
+
+fn print_table(rows: &[[String; 6]]) {
+    // can safely unwrap because we know there is at least one value.
+    let name_width = rows.iter().map(|r| r[0].len()).max().unwrap().max(8);
+    let status_width = 8;
+    let clients_width = 7;
+    let windows_width = 7;
+    let panes_width = 5;
+
+    println!(
+        "{:^name_width$}| {:^status_width$} | {:^clients_width$} | {:^windows_width$} | {:^panes_width$} | UPTIME",
+        "NAME", "STATUS", "CLIENTS", "WINDOWS", "PANES"
+    );
+    for [name, status, clients, windows, panes, uptime] in rows {
+        println!(
+            "{name:name_width$}| {status:status_width$} | {clients:>clients_width$} | {windows:>windows_width$} | {panes:>panes_width$} | {uptime}"
+        );
+    }
+}
+
+/// The columns of the Space, group or orphan session, in the table.
+fn row(name: &str, status: &str, session: Option<&Session>) -> [String; 6] {
+    let Some(session) = session else {
+        return [
+            name.to_string(),
+            status.to_string(),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+        ];
+    };
+
+    [
+        name.to_string(),
+        status.to_string(),
+        session.clients.to_string(),
+        session.windows.to_string(),
+        session.panes.to_string(),
+        utils::format_duration(utils::now().saturating_sub(session.created_at))
+            .unwrap_or_else(|| String::from("<1min")),
+    ]
+}
+
+/// The running tmux sessions, by name.
+///
+/// There is none if the tmux server isn't running.
+pub fn sessions() -> Result<HashMap<String, Session>> {
+    // tmux replaces the tabs of the output, the names are quoted instead.
+    let output = Tmux::with_command(
+        ListSessions::new()
+            .format("#{q:session_name} #{session_attached} #{session_windows} #{session_created}"),
+    )
+    .output()?;
+    if !output.success() {
+        return Ok(HashMap::new());
+    }
+
+    let mut sessions = String::from_utf8_lossy(&output.stdout())
+        .lines()
+        .filter_map(|line| {
+            let mut fields = shlex::split(line)?.into_iter();
+            let name = fields.next()?;
+            let session = Session {
+                clients: fields.next()?.parse().ok()?,
+                windows: fields.next()?.parse().ok()?,
+                window_names: Vec::new(),
+                panes: 0,
+                created_at: fields.next()?.parse().ok()?,
+            };
+            Some((name, session))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let windows = Tmux::with_command(
+        ListWindows::new()
+            .all()
+            .format("#{q:session_name} #{q:window_name}"),
+    )
+    .output()?;
+    for line in String::from_utf8_lossy(&windows.stdout()).lines() {
+        let mut fields = shlex::split(line).unwrap_or_default().into_iter();
+        if let (Some(name), Some(window)) = (fields.next(), fields.next())
+            && let Some(session) = sessions.get_mut(&name)
+        {
+            session.window_names.push(window);
+        }
+    }
+
+    let panes = Tmux::with_command(ListPanes::new().all().format("#{q:session_name}")).output()?;
+    for line in String::from_utf8_lossy(&panes.stdout()).lines() {
+        let name = shlex::split(line).and_then(|fields| fields.into_iter().next());
+        if let Some(session) = name.and_then(|name| sessions.get_mut(&name)) {
+            session.panes += 1;
+        }
+    }
+
+    Ok(sessions)
+}
@@ -0,0 +1,225 @@
+//! The `stop` and `restart` commands.
+
+use std::{
+    collections::HashSet,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use tmux_interface::{HasSession, KillSession, KillWindow, ListPanes, SendKeys, Tmux, TmuxCommand};
+
+use crate::{
+    Context, DsError, Result,
+    cmds::{go, status},
+    config::Hook,
+    hooks, utils,
+};
+
+/// How often the panes are checked while waiting for them to exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub fn command(ctx: &mut Context, space_name: String, graceful: bool, timeout: u64) -> Result {
+    // the Space must exist even if it isn't running.
+    ctx.db.get_space(&space_name)?;
+
+    if !stop_space(ctx, &space_name, graceful, timeout)? {
+        return Err(DsError::NotRunning(space_name));
+    }
+    hooks::run(ctx, &space_name, Hook::Stop)?;
+
+    println!("Stopped the space {space_name:?}.");
+    Ok(())
+}
+
+/// Stops every running Space, and the sessions combining groups.
+pub fn all(ctx: &mut Context, graceful: bool, timeout: u64) -> Result {
+    let mut spaces = ctx
+        .db
+        .spaces_iter()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+    spaces.sort();
+    let mut groups = ctx.db.groups_iter().collect::<Vec<_>>();
+    groups.sort();
+
+    let mut stopped = 0;
+    let mut failed = 0;
+    // the Spaces with a window in a combined session are stopped along with
+    // it.
+    let sessions = status::sessions()?;
+    let mut grouped = HashSet::new();
+    for (group_name, members) in groups {
+        let session_name = ctx.group_session_name(group_name);
+        let windows = sessions
+            .get(&session_name)
+            .map(|session| session.window_names.as_slice())
+            .unwrap_or_default();
+        if stop_session(&session_name, graceful, timeout)? {
+            println!("Stopped the combined session of the group {group_name:?}.");
+            grouped.extend(members.iter().filter(|member| windows.contains(member)));
+            stopped += 1;
+        }
+    }
+    for space_name in spaces {
+        if stop_session(&ctx.session_name(space_name), graceful, timeout)?
+            || grouped.contains(space_name)
+        {
+            println!("Stopped the space {space_name:?}.");
+            if !run_stop_hooks(ctx, space_name) {
+                failed += 1;
+            }
+            stopped += 1;
+        }
+    }
+
+    if stopped == 0 {
+        println!("No space is running.");
+    }
+    if failed > 0 {
+        return Err(DsError::HooksFailed(failed));
+    }
+    Ok(())
+}
+
+/// Stops every Space of the group, and the session combining them if any.
+pub fn group(ctx: &mut Context, group_name: String, graceful: bool, timeout: u64) -> Result {
+    let members = ctx.db.get_group(&group_name)?.to_vec();
+
+    let mut stopped = 0;
+    let mut failed = 0;
+    let session_name = ctx.group_session_name(&group_name);
+    let combined = stop_session(&session_name, graceful, timeout)?;
+    if combined {
+        println!("Stopped the combined session of the group {group_name:?}.");
+        stopped += 1;
+    }
+    for space_name in &members {
+        if stop_session(&ctx.session_name(space_name), graceful, timeout)? {
+            println!("Stopped the space {space_name:?}.");
+            stopped += 1;
+        } else if !combined {
+            continue;
+        }
+        if !run_stop_hooks(ctx, space_name) {
+            failed += 1;
+        }
+    }
+
+    if stopped == 0 {
+        println!("No space of the group {group_name:?} is running.");
+    }
+    if failed > 0 {
+        return Err(DsError::HooksFailed(failed));
+    }
+    Ok(())
+}
+
+/// Runs the `on_stop` hooks of the Space, when stopping several Spaces.
+///
+/// A failure is reported rather than returned so the other Spaces are still
+/// stopped, returns `false` if it failed.
+fn run_stop_hooks(ctx: &Context, space_name: &str) -> bool {
+    match hooks::run(ctx, space_name, Hook::Stop) {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("ERROR: {err}");
+            false
+        }
+    }
+}
+
+/// Stops the Space if it is running, then goes to it again, with its current
+/// tree.
+pub fn restart(ctx: &mut Context, space_name: String, graceful: bool, timeout: u64) -> Result {
+    ctx.db.get_space(&space_name)?;
+
+    if stop_space(ctx, &space_name, graceful, timeout)? {
+        hooks::run(ctx, &space_name, Hook::Stop)?;
+    }
+
+    go::command(ctx, space_name, false, false, false)
+}
+
+/// Kills the session of the Space and its windows in the combined sessions
+/// of its groups, returns `false` if it wasn't running.
+fn stop_space(ctx: &Context, space_name: &str, graceful: bool, timeout: u64) -> Result<bool> {
+    let mut stopped = stop_session(&ctx.session_name(space_name), graceful, timeout)?;
+    for session_name in ctx.combined_session_names(space_name) {
+        stopped |= stop_window(&session_name, space_name, graceful, timeout)?;
+    }
+    Ok(stopped)
+}
+
+/// Kills the tmux session, returns `false` if it wasn't running.
+///
+/// If `graceful`, sends `C-c` to every pane first and waits up to `timeout`
+/// seconds for them to go back to the shell.
+pub fn stop_session(session_name: &str, graceful: bool, timeout: u64) -> Result<bool> {
+    let target = Context::session_target(session_name);
+    let kill = KillSession::new().target_session(&target).into();
+    stop_target(&target, true, kill, session_name, graceful, timeout)
+}
+
+/// Kills the window of the Space in the session, returns `false` if there is
+/// none, see [`stop_session`].
+fn stop_window(session_name: &str, space_name: &str, graceful: bool, timeout: u64) -> Result<bool> {
+    // the windows of a combined session are named after their Space.
+    let target = format!("{}:={space_name}", Context::session_target(session_name));
+    let kill = KillWindow::new().target_window(&target).into();
+    stop_target(&target, false, kill, session_name, graceful, timeout)
+}
+
+/// Runs `kill` once the panes of the target, a whole session if `session`,
+/// are stopped. Returns `false` if the target doesn't exist.
+fn stop_target(
+    target: &str,
+    session: bool,
+    kill: TmuxCommand,
+    session_name: &str,
+    graceful: bool,
+    timeout: u64,
+) -> Result<bool> {
+    let running = Tmux::with_command(HasSession::new().target_session(target))
+        .output()?
+        .success();
+    if !running {
+        return Ok(false);
+    }
+
+    if graceful {
+        for pane in pane_fields(target, session, "#{pane_id}")? {
+            Tmux::with_command(SendKeys::new().target_pane(&pane).key("C-c")).output()?;
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(timeout);
+        while Instant::now() < deadline {
+            let commands = pane_fields(target, session, "#{pane_current_command}")?;
+            if commands.iter().all(|command| utils::is_shell(command)) {
+                break;
+            }
+            sleep(POLL_INTERVAL);
+        }
+    }
+
+    let output = Tmux::with_command(kill).output()?;
+    if !output.success() {
+        return Err(DsError::CantStop(
+            session_name.to_string(),
+            String::from_utf8_lossy(&output.stderr()).trim().to_string(),
+        ));
+    }
+    Ok(true)
+}
+
+/// The field, in the tmux format, of every pane of the target, a whole
+/// session if `session`.
+fn pane_fields(target: &str, session: bool, format: &str) -> Result<Vec<String>> {
+    let list = ListPanes::new().target(target).format(format);
+    let list = if session { list.session() } else { list };
+    let output = Tmux::with_command(list).output()?;
+
+    Ok(String::from_utf8_lossy(&output.stdout())
+        .lines()
+        .map(String::from)
+        .collect())
+}
@@ -2,7 +2,10 @@
 
 use crate::{Context, Result};
 
-pub fn command(ctx: &Context, space_name: String) -> Result {
+pub fn command(ctx: &mut Context, space_name: String) -> Result {
+    // run by the shell wrapper, never rewrite the files.
+    ctx.set_read_only();
+
     let space = ctx.db.get_space(&space_name)?;
 
     println!("{}", space.wdir.to_string_lossy());
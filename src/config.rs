@@ -1,14 +1,21 @@
-use std::{collections::HashMap, fmt::Debug, io::Write};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs::{read_dir, read_to_string},
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tmux_interface::{SelectPane, SendKeys, SplitWindow, TmuxCommands};
+use tmux_interface::{SelectPane, SendKeys, SetOption, SplitWindow, TmuxCommand, TmuxCommands};
 
-use crate::{DsError, Result, database::Space};
+use crate::{DsError, Result, database::Space, format::Format, migrations::CONFIG_VERSION};
 
 /// A tree, represents what the environment will look like.
 //  /!\ If a tree is create update the `new-tree` command.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub enum SpaceTree {
     /// A command to run, the format is special.
     ///
@@ -22,6 +29,10 @@ pub enum SpaceTree {
     TmuxVSplit {
         lhs: Option<Box<SpaceTree>>,
         rhs: Option<Box<SpaceTree>>,
+        /// Percentage of the pane taken by the left side, split in halves if
+        /// not set.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size: Option<u8>,
     },
     /// Launch tmux if not already in a Tmux session and split the pane in two
     /// horizontally. A Space Tree will be applied to the top and one to the
@@ -29,63 +40,81 @@ pub enum SpaceTree {
     TmuxHSplit {
         top: Option<Box<SpaceTree>>,
         bottom: Option<Box<SpaceTree>>,
+        /// Percentage of the pane taken by the top side, split in halves if
+        /// not set.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size: Option<u8>,
     },
 }
 
 impl SpaceTree {
-    pub fn build<'a>(&self, space: &Space, space_name: &'a str) -> Result<TmuxCommands<'a>> {
+    /// Builds the tmux commands applying the tree to the pane.
+    ///
+    /// If `record`, the command sent to every pane is recorded in
+    /// [`PANE_CMD_OPTION`] for `go --reload`.
+    pub fn build<'a>(
+        &self,
+        space: &Space,
+        space_name: &'a str,
+        record: bool,
+    ) -> Result<TmuxCommands<'a>> {
         match self {
             Self::Cmd(cmd) => {
                 let parsed_cmd = cmd_placeholders(cmd, space)?;
-                let cmds = TmuxCommands::new()
+                let mut cmds = TmuxCommands::new()
                     .add_command(
                         SendKeys::new()
                             .target_pane(space_name)
-                            .key(parsed_cmd)
+                            .key(parsed_cmd.clone())
                             .into(),
                     )
                     .add_command(SendKeys::new().target_pane(space_name).key("C-m").into());
+                if record {
+                    cmds.push(record_cmd(space_name, parsed_cmd));
+                }
                 Ok(cmds)
             }
-            Self::TmuxVSplit { lhs, rhs } => {
+            Self::TmuxVSplit { lhs, rhs, size } => {
                 let mut cmds = TmuxCommands::new();
 
                 // push the split first
-                cmds.push(SplitWindow::new().horizontal().target_window(space_name));
+                let split = SplitWindow::new().horizontal().target_window(space_name);
+                cmds.push(sized_split(split, *size));
 
                 // then push the left
                 if let Some(lhs) = lhs {
-                    cmds.push(SelectPane::new().left());
-                    let lhs = lhs.build(space, space_name)?;
+                    cmds.push(SelectPane::new().left().target_pane(space_name));
+                    let lhs = lhs.build(space, space_name, record)?;
                     cmds.push_cmds(lhs);
                 }
 
                 // finally push the right
                 if let Some(rhs) = rhs {
-                    cmds.push(SelectPane::new().right());
-                    let rhs = rhs.build(space, space_name)?;
+                    cmds.push(SelectPane::new().right().target_pane(space_name));
+                    let rhs = rhs.build(space, space_name, record)?;
                     cmds.push_cmds(rhs);
                 }
 
                 Ok(cmds)
             }
-            Self::TmuxHSplit { top, bottom } => {
+            Self::TmuxHSplit { top, bottom, size } => {
                 let mut cmds = TmuxCommands::new();
 
                 // push the split first
-                cmds.push(SplitWindow::new().vertical().target_window(space_name));
+                let split = SplitWindow::new().vertical().target_window(space_name);
+                cmds.push(sized_split(split, *size));
 
                 // then push the top
                 if let Some(top) = top {
-                    cmds.push(SelectPane::new().up());
-                    let top = top.build(space, space_name)?;
+                    cmds.push(SelectPane::new().up().target_pane(space_name));
+                    let top = top.build(space, space_name, record)?;
                     cmds.push_cmds(top);
                 }
 
                 // finally push the bottom
                 if let Some(bottom) = bottom {
-                    cmds.push(SelectPane::new().down());
-                    let bottom = bottom.build(space, space_name)?;
+                    cmds.push(SelectPane::new().down().target_pane(space_name));
+                    let bottom = bottom.build(space, space_name, record)?;
                     cmds.push_cmds(bottom);
                 }
 
@@ -94,6 +123,32 @@ impl SpaceTree {
         }
     }
 
+    /// Calls `f` on this tree and every tree inside it, with the path to reach
+    /// it from this tree, like `lhs.top`, empty for this tree.
+    pub fn walk(&self, f: &mut impl FnMut(&str, &SpaceTree)) {
+        self.walk_inner(String::new(), f);
+    }
+
+    fn walk_inner(&self, path: String, f: &mut impl FnMut(&str, &SpaceTree)) {
+        f(&path, self);
+
+        let children = match self {
+            Self::Cmd(_) => return,
+            Self::TmuxVSplit { lhs, rhs, .. } => [("lhs", lhs), ("rhs", rhs)],
+            Self::TmuxHSplit { top, bottom, .. } => [("top", top), ("bottom", bottom)],
+        };
+        for (name, child) in children {
+            if let Some(child) = child {
+                let child_path = if path.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{path}.{name}")
+                };
+                child.walk_inner(child_path, f);
+            }
+        }
+    }
+
     pub const PRINT_INDENT: usize = 2;
 
     /// Prints the Tree with a Pretty AST like syntax.
@@ -101,9 +156,13 @@ impl SpaceTree {
     /// Do not flush the Writer, you may need to `flush` it.
     pub fn pretty_print(&self, w: &mut impl Write, indent: usize) -> Result {
         match self {
-            Self::TmuxVSplit { lhs, rhs } => {
+            Self::TmuxVSplit { lhs, rhs, size } => {
                 writeln!(w, "TmuxVSplit:")?;
 
+                if let Some(size) = size {
+                    writeln!(w, "{:indent$}  | size: {size}%", "")?;
+                }
+
                 write!(w, "{:indent$}  | lhs: ", "")?;
                 if let Some(lhs) = lhs {
                     lhs.pretty_print(w, indent + Self::PRINT_INDENT)?;
@@ -118,9 +177,13 @@ impl SpaceTree {
                     writeln!(w, "None")?;
                 }
             }
-            Self::TmuxHSplit { top, bottom } => {
+            Self::TmuxHSplit { top, bottom, size } => {
                 writeln!(w, "TmuxHSplit:")?;
 
+                if let Some(size) = size {
+                    writeln!(w, "{:indent$}  | size: {size}%", "")?;
+                }
+
                 write!(w, "{:indent$}  | top: ", "")?;
                 if let Some(top) = top {
                     top.pretty_print(w, indent + Self::PRINT_INDENT)?;
@@ -142,6 +205,30 @@ impl SpaceTree {
     }
 }
 
+/// The pane option holding the command sent to the pane, `go --reload`
+/// compares it with the one of the tree.
+pub const PANE_CMD_OPTION: &str = "@devspace_cmd";
+
+/// Records the command sent to the pane in [`PANE_CMD_OPTION`].
+pub(crate) fn record_cmd<'a>(target_pane: &'a str, cmd: String) -> TmuxCommand<'a> {
+    SetOption::new()
+        .pane()
+        .target_pane(target_pane)
+        .option(PANE_CMD_OPTION)
+        .value(cmd)
+        .build()
+}
+
+/// The split, with the size of the new pane, the right or bottom one, if the
+/// split has a size.
+pub(crate) fn sized_split<'a>(split: SplitWindow<'a>, size: Option<u8>) -> TmuxCommand<'a> {
+    let mut split = split.build();
+    if let Some(size) = size {
+        split.push_option("-l", format!("{}%", 100u8.saturating_sub(size)));
+    }
+    split
+}
+
 /// Cmd Parsing Error.
 #[derive(Error, Debug)]
 pub enum CmdParsingError {
@@ -153,55 +240,66 @@ pub enum CmdParsingError {
     OpeningBracketNoClosing,
 }
 
-fn cmd_placeholders(cmd: &str, space: &Space) -> Result<String> {
-    fn cmd_placeholders_inner(cmd: &str, space: &Space) -> Result<String, CmdParsingError> {
-        let mut res = String::new();
-        let mut key = None;
-        let mut chars = cmd.chars().peekable();
-
-        while let Some(ch) = chars.next() {
-            match ch {
-                '{' => {
-                    if chars.peek() == Some(&'{') {
-                        chars.next(); // Consume second '{'
-                        res.push('{');
-                    } else {
-                        key = Some(String::new());
-                    }
-                }
-                '}' => {
-                    if chars.peek() == Some(&'}') {
-                        chars.next(); // Consume second '}'
-                        res.push('}');
-                    } else if let Some(k) = key.take() {
-                        let replacement: String = match k.as_str() {
-                            "Space.wdir" => space.wdir.clone().to_string_lossy().into_owned(),
-                            _ => return Err(CmdParsingError::UnknownPlaceholder(k)),
-                        };
-                        res.push_str(&replacement);
-                    } else {
-                        return Err(CmdParsingError::ClosingBracketNoOpening);
-                    }
+/// The placeholders that can be used in a `Cmd`.
+pub const PLACEHOLDERS: &[&str] = &["Space.wdir"];
+
+pub(crate) fn cmd_placeholders(cmd: &str, space: &Space) -> Result<String> {
+    expand_placeholders(cmd, |key| match key {
+        "Space.wdir" => Some(space.wdir.to_string_lossy().into_owned()),
+        _ => None,
+    })
+    .map_err(DsError::CmdParsingError)
+}
+
+/// Replaces the placeholders of the command by what `resolve` returns for
+/// their key, `None` meaning the placeholder is unknown.
+pub fn expand_placeholders(
+    cmd: &str,
+    mut resolve: impl FnMut(&str) -> Option<String>,
+) -> Result<String, CmdParsingError> {
+    let mut res = String::new();
+    let mut key = None;
+    let mut chars = cmd.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' => {
+                if chars.peek() == Some(&'{') {
+                    chars.next(); // Consume second '{'
+                    res.push('{');
+                } else {
+                    key = Some(String::new());
                 }
-                _ => {
-                    if let Some(ref mut k) = key {
-                        k.push(ch);
-                    } else {
-                        res.push(ch);
-                    }
+            }
+            '}' => {
+                if chars.peek() == Some(&'}') {
+                    chars.next(); // Consume second '}'
+                    res.push('}');
+                } else if let Some(k) = key.take() {
+                    let Some(replacement) = resolve(&k) else {
+                        return Err(CmdParsingError::UnknownPlaceholder(k));
+                    };
+                    res.push_str(&replacement);
+                } else {
+                    return Err(CmdParsingError::ClosingBracketNoOpening);
                 }
             }
-        }
-        if let Some(k) = key.take() {
-            if !k.is_empty() {
-                return Err(CmdParsingError::OpeningBracketNoClosing);
+            _ => {
+                if let Some(ref mut k) = key {
+                    k.push(ch);
+                } else {
+                    res.push(ch);
+                }
             }
         }
-
-        Ok(res)
+    }
+    if let Some(k) = key.take()
+        && !k.is_empty()
+    {
+        return Err(CmdParsingError::OpeningBracketNoClosing);
     }
 
-    cmd_placeholders_inner(cmd, space).map_err(DsError::CmdParsingError)
+    Ok(res)
 }
 
 #[derive(Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
@@ -220,10 +318,107 @@ impl From<&str> for SpaceTreeId {
     }
 }
 
+/// Commands run outside of the panes around the life of a Space, in its
+/// working directory with the placeholders of a `Cmd`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Hooks {
+    /// Run by `go` before launching the Space.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_start: Option<String>,
+    /// Run by `go` before attaching to the Space, launched or not, or opening
+    /// it in a new window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_attach: Option<String>,
+    /// Run by `go` once detached from the Space, outside of tmux only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_detach: Option<String>,
+    /// Run by `stop` and `restart` once the Space is stopped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_stop: Option<String>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.on_start.is_none()
+            && self.on_attach.is_none()
+            && self.on_detach.is_none()
+            && self.on_stop.is_none()
+    }
+
+    /// The command of the hook, if set.
+    pub fn get(&self, hook: Hook) -> Option<&str> {
+        match hook {
+            Hook::Start => self.on_start.as_deref(),
+            Hook::Attach => self.on_attach.as_deref(),
+            Hook::Detach => self.on_detach.as_deref(),
+            Hook::Stop => self.on_stop.as_deref(),
+        }
+    }
+
+    /// Sets the hooks set in `other`, an empty command unsets the hook.
+    pub fn update(&mut self, other: Hooks) {
+        let update = |hook: &mut Option<String>, new: Option<String>| {
+            if let Some(new) = new {
+                *hook = (!new.is_empty()).then_some(new);
+            }
+        };
+        update(&mut self.on_start, other.on_start);
+        update(&mut self.on_attach, other.on_attach);
+        update(&mut self.on_detach, other.on_detach);
+        update(&mut self.on_stop, other.on_stop);
+    }
+}
+
+/// The moments of the life of a Space a hook can run at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    Start,
+    Attach,
+    Detach,
+    Stop,
+}
+
+impl Hook {
+    pub const ALL: [Hook; 4] = [Hook::Start, Hook::Attach, Hook::Detach, Hook::Stop];
+
+    /// Name of the hook, as written in the files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Hook::Start => "on_start",
+            Hook::Attach => "on_attach",
+            Hook::Detach => "on_detach",
+            Hook::Stop => "on_stop",
+        }
+    }
+}
+
+/// Name of the directory, in the devspace directory, where every file in a
+/// supported format is included in the config.
+pub const TREES_DIR: &str = "trees.d";
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
+    /// Version of the layout of the config, see [`crate::migrations`].
+    pub version: u32,
     pub default_tree: SpaceTreeId,
+    /// Other files defining trees, relative to the devspace directory.
+    ///
+    /// Every file contains a map of trees, like the `trees` field, in the
+    /// format of its extension.
+    #[serde(default)]
+    pub include: Vec<PathBuf>,
     pub(crate) trees: HashMap<SpaceTreeId, SpaceTree>,
+    /// The hooks of the Spaces using a tree, by the name of the tree.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub hooks: HashMap<SpaceTreeId, Hooks>,
+    /// The env files of the Spaces using a tree, by the name of the tree, see
+    /// [`crate::env_file`].
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env_files: HashMap<SpaceTreeId, Vec<PathBuf>>,
+    /// The trees that come from included files, with their file. They are
+    /// merged into `trees` but never written back to the config.
+    #[serde(skip)]
+    pub(crate) included: HashMap<SpaceTreeId, PathBuf>,
 }
 
 impl Config {
@@ -233,25 +428,105 @@ impl Config {
             .ok_or(DsError::SpaceTreeNotFound(key.clone()))
     }
 
-    pub fn insert_tree(&mut self, tree_name: String, tree: SpaceTree) {
-        self.trees.insert(SpaceTreeId(tree_name), tree);
+    /// The file the tree was included from, `None` if it is defined in the
+    /// config itself.
+    pub fn tree_source(&self, key: &SpaceTreeId) -> Option<&Path> {
+        self.included.get(key).map(PathBuf::as_path)
+    }
+
+    pub fn insert_tree(&mut self, tree_name: String, tree: SpaceTree) -> Result {
+        let id = SpaceTreeId(tree_name);
+        if let Some(file) = self.included.get(&id) {
+            return Err(DsError::IncludedTree(id, file.clone()));
+        }
+
+        self.trees.insert(id, tree);
+        Ok(())
+    }
+
+    pub fn remove_tree(&mut self, tree_name: String) -> Result {
+        let id = SpaceTreeId(tree_name);
+        if let Some(file) = self.included.get(&id) {
+            return Err(DsError::IncludedTree(id, file.clone()));
+        }
+
+        self.trees.remove(&id);
+        Ok(())
+    }
+
+    /// Merges the trees of the included files and of the files in the
+    /// [`TREES_DIR`] of the devspace directory.
+    ///
+    /// `conf_path` is the path of the config, to report trees defined both in
+    /// it and an included file.
+    pub fn load_includes(&mut self, dir: &Path, conf_path: &Path) -> Result {
+        let mut files = self
+            .include
+            .iter()
+            .map(|file| dir.join(file))
+            .collect::<Vec<_>>();
+
+        let trees_dir = dir.join(TREES_DIR);
+        if trees_dir.is_dir() {
+            let mut entries = read_dir(&trees_dir)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            entries.retain(|path| Format::from_path(path).is_some());
+            entries.sort();
+            files.extend(entries);
+        }
+
+        for (idx, file) in files.into_iter().enumerate() {
+            let format = Format::from_path(&file).unwrap_or_default();
+            let buf = read_to_string(&file).map_err(|err| {
+                if idx < self.include.len() {
+                    DsError::CantReadInclude(conf_path.to_path_buf(), file.clone(), err)
+                } else {
+                    DsError::CantReadTrees(file.clone(), err)
+                }
+            })?;
+            let trees: HashMap<SpaceTreeId, SpaceTree> = format.parse_file(&file, &buf)?;
+
+            for (id, tree) in trees {
+                if let Some(other) = self.included.get(&id) {
+                    return Err(DsError::DuplicateTree(id, other.clone(), file));
+                }
+                if self.trees.contains_key(&id) {
+                    return Err(DsError::DuplicateTree(id, conf_path.to_path_buf(), file));
+                }
+
+                self.included.insert(id.clone(), file.clone());
+                self.trees.insert(id, tree);
+            }
+        }
+
+        Ok(())
     }
 
-    pub fn remove_tree(&mut self, tree_name: String) {
-        self.trees.remove(&SpaceTreeId(tree_name));
+    /// The config as it is written back, without the included trees.
+    pub(crate) fn without_included(&self) -> Config {
+        let mut config = self.clone();
+        config.trees.retain(|id, _| !self.included.contains_key(id));
+        config.included.clear();
+        config
     }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
+            version: CONFIG_VERSION,
             default_tree: "jump".into(),
+            include: Vec::new(),
             trees: HashMap::from([(
                 "jump".into(),
                 SpaceTree::Cmd(
                     "clear && echo 'Hello, welcome to the default devspace's tree'".to_string(),
                 ),
             )]),
+            hooks: HashMap::new(),
+            env_files: HashMap::new(),
+            included: HashMap::new(),
         }
     }
 }
@@ -1,15 +1,47 @@
 use std::{
+    cmp::Reverse,
     collections::{HashMap, hash_map::Iter},
-    path::PathBuf,
+    env::current_dir,
+    fs::canonicalize,
+    path::{Path, PathBuf},
 };
 
-use crate::{DsError, Result, config::SpaceTreeId};
+use crate::{
+    DsError, Result,
+    config::{Hooks, SpaceTreeId},
+    migrations::DB_VERSION,
+    utils,
+};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DataBase {
+    /// Version of the layout of the database, see [`crate::migrations`].
+    pub version: u32,
     entries: HashMap<String, Space>,
+    /// Name of the last used Space.
+    #[serde(default)]
+    last_space: Option<String>,
+    /// The trusted project files with the SHA-256 of their content when
+    /// trusted, see [`crate::project`].
+    #[serde(default)]
+    trusted_projects: HashMap<PathBuf, String>,
+    /// Groups of Spaces launched together, with the name of their members.
+    #[serde(default)]
+    groups: HashMap<String, Vec<String>>,
+}
+
+impl Default for DataBase {
+    fn default() -> Self {
+        DataBase {
+            version: DB_VERSION,
+            entries: HashMap::new(),
+            last_space: None,
+            trusted_projects: HashMap::new(),
+            groups: HashMap::new(),
+        }
+    }
 }
 
 impl DataBase {
@@ -38,6 +70,18 @@ impl DataBase {
         self.entries.iter()
     }
 
+    /// Names of the Spaces using the given tree, sorted.
+    pub fn spaces_using_tree(&self, tree: &SpaceTreeId) -> Vec<&String> {
+        let mut spaces = self
+            .entries
+            .iter()
+            .filter(|(_, space)| &space.tree == tree)
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        spaces.sort();
+        spaces
+    }
+
     /// Is any Space contained?
     pub fn is_empty(&self) -> bool {
         self.entries.is_empty()
@@ -47,6 +91,94 @@ impl DataBase {
     /// doesn't exists.
     pub fn remove(&mut self, key: &str) {
         self.entries.remove(key);
+
+        if self.last_space.as_deref() == Some(key) {
+            self.last_space = None;
+        }
+
+        // a group left without members is removed too.
+        for members in self.groups.values_mut() {
+            members.retain(|member| member != key);
+        }
+        self.groups.retain(|_, members| !members.is_empty());
+    }
+
+    /// Retrieve the members of the group from its name.
+    pub fn get_group(&self, group: &str) -> Result<&[String]> {
+        self.groups
+            .get(group)
+            .map(Vec::as_slice)
+            .ok_or_else(|| DsError::GroupNotFound(group.to_string()))
+    }
+
+    /// Inserts a new group with the given name (the key), if a group with the
+    /// same name already exists it will be overwritten.
+    pub fn insert_group(&mut self, key: String, members: Vec<String>) {
+        self.groups.insert(key, members);
+    }
+
+    /// Remove the group with the name provided as argument, does nothing if it
+    /// doesn't exists.
+    pub fn remove_group(&mut self, key: &str) {
+        self.groups.remove(key);
+    }
+
+    /// Iterator over the groups
+    pub fn groups_iter(&self) -> Iter<'_, String, Vec<String>> {
+        self.groups.iter()
+    }
+
+    /// Name of the last used Space, if it still exists.
+    pub fn last_space(&self) -> Option<&str> {
+        self.last_space
+            .as_deref()
+            .filter(|name| self.entries.contains_key(*name))
+    }
+
+    /// Remembers the Space as the last used one.
+    pub fn set_last_space(&mut self, key: &str) {
+        self.last_space = Some(key.to_string());
+    }
+
+    /// Was the project file trusted with exactly this content?
+    pub fn is_trusted(&self, project_file: &Path, content: &str) -> bool {
+        self.trusted_projects
+            .get(project_file)
+            .is_some_and(|trusted| *trusted == utils::sha256(content))
+    }
+
+    /// Was the project file ever trusted, with any content?
+    pub fn is_known_project(&self, project_file: &Path) -> bool {
+        self.trusted_projects.contains_key(project_file)
+    }
+
+    /// Trusts the project file with this content.
+    pub fn trust(&mut self, project_file: PathBuf, content: &str) {
+        self.trusted_projects
+            .insert(project_file, utils::sha256(content));
+    }
+
+    /// Retrieve the Space whose working directory is the nearest ancestor of
+    /// `path`, or `path` itself.
+    ///
+    /// Both paths are canonicalized so symlinks are resolved.
+    pub fn space_containing(&self, path: &Path) -> Option<(&String, &Space)> {
+        let path = canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+        self.entries
+            .iter()
+            .filter_map(|(name, space)| {
+                let wdir = canonicalize(&space.wdir).unwrap_or_else(|_| space.wdir.clone());
+                path.starts_with(&wdir)
+                    .then(|| (wdir.components().count(), Reverse(name), space))
+            })
+            .max_by_key(|(depth, name, _)| (*depth, *name))
+            .map(|(_, Reverse(name), space)| (name, space))
+    }
+
+    /// Retrieve the Space containing the current directory.
+    pub fn current_space(&self) -> Result<Option<(&String, &Space)>> {
+        Ok(self.space_containing(&current_dir()?))
     }
 }
 
@@ -57,10 +189,70 @@ pub struct Space {
     /// the tree of Space, how to launch it.
     #[serde(rename = "tree")]
     pub tree: SpaceTreeId,
+    /// a short description of the Space.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// tags used to filter the Spaces.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// when the Space was created, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub created_at: Option<u64>,
+    /// when the Space was last used with `go`, in seconds since the Unix
+    /// epoch.
+    #[serde(default)]
+    pub last_used_at: Option<u64>,
+    /// how many times the Space was launched, attaching to an already
+    /// launched Space isn't counted.
+    #[serde(default)]
+    pub launch_count: u32,
+    /// commands run around the life of the Space, after the ones of its
+    /// tree.
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+    /// env files loaded in the session of the Space, relative to its working
+    /// directory, after the ones of its tree.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_files: Vec<PathBuf>,
 }
 
 impl Space {
     pub fn new(wdir: PathBuf, tree: SpaceTreeId) -> Space {
-        Space { wdir, tree }
+        Space {
+            wdir,
+            tree,
+            description: None,
+            tags: Vec::new(),
+            created_at: Some(utils::now()),
+            last_used_at: None,
+            launch_count: 0,
+            hooks: Hooks::default(),
+            env_files: Vec::new(),
+        }
+    }
+
+    /// Does the Space have this tag?
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Adds the tag to the Space, does nothing if it already has it.
+    pub fn add_tag(&mut self, tag: String) {
+        if !self.has_tag(&tag) {
+            self.tags.push(tag);
+        }
+    }
+
+    /// Removes the tag from the Space, does nothing if it doesn't have it.
+    pub fn remove_tag(&mut self, tag: &str) {
+        self.tags.retain(|t| t != tag);
+    }
+
+    /// Marks the Space as used now, and as launched if `launched` is true.
+    pub fn mark_used(&mut self, launched: bool) {
+        self.last_used_at = Some(utils::now());
+        if launched {
+            self.launch_count += 1;
+        }
     }
 }
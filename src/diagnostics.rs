@@ -0,0 +1,294 @@
+//! Human friendly parsing errors, naming the file, showing the offending line
+//! and suggesting close matches for unknown names.
+
+use std::{
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+};
+
+use ron::error::{Error as RonError, SpannedError};
+
+use crate::{DsError, utils};
+
+/// An unknown name found in a file, with the names that were expected.
+type Unknown = (String, Vec<String>);
+
+/// An error while parsing a file.
+#[derive(Debug)]
+pub struct ParseError {
+    /// The file that failed to parse.
+    pub file: PathBuf,
+    /// What went wrong.
+    pub message: String,
+    /// Where it went wrong, line and column starting at 1.
+    pub position: Option<(usize, usize)>,
+    /// The line where it went wrong.
+    pub line: Option<String>,
+    /// A close match of the unknown name, if any.
+    pub suggestion: Option<String>,
+}
+
+impl ParseError {
+    /// Adds the file and its content to an error returned while parsing it.
+    ///
+    /// Errors that are not parsing errors are returned untouched.
+    pub fn from_error(file: &Path, buf: &str, err: DsError) -> DsError {
+        let (message, position, unknown) = match err {
+            DsError::FileParsingError(err) => ron_error(err),
+            DsError::TomlParsingError(err) => {
+                let position = err.span().map(|span| position_of(buf, span.start));
+                let message = err.message().to_string();
+                let unknown = unknown_name(&message);
+                (message, position, unknown)
+            }
+            DsError::YamlError(err) => {
+                let position = err.location().map(|loc| (loc.line(), loc.column()));
+                let message = strip_position(err.to_string(), position);
+                let unknown = unknown_name(&message);
+                (message, position, unknown)
+            }
+            DsError::JsonError(err) if err.line() != 0 => {
+                let position = Some((err.line(), err.column()));
+                let message = strip_position(err.to_string(), position);
+                let unknown = unknown_name(&message);
+                (message, position, unknown)
+            }
+            err => return err,
+        };
+
+        let line = position.and_then(|(line, _)| buf.lines().nth(line.saturating_sub(1)));
+
+        let position = match (position, line, &unknown) {
+            (Some((line_nb, col)), Some(line), Some((found, _))) => {
+                Some((line_nb, caret_column(line, col, found)))
+            }
+            _ => position,
+        };
+
+        let suggestion = unknown.and_then(|(found, expected)| {
+            let expected = expected.iter().map(String::as_str).collect::<Vec<_>>();
+            utils::closest(&found, &expected).map(String::from)
+        });
+
+        DsError::ParseError(Box::new(ParseError {
+            file: file.to_path_buf(),
+            message: without_snippet(&message),
+            position,
+            line: line.map(String::from),
+            suggestion,
+        }))
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to parse {}", self.file.display())?;
+        if let Some((line, col)) = self.position {
+            write!(f, ":{line}:{col}")?;
+        }
+        write!(f, ", {}", self.message)?;
+
+        if let (Some((line_nb, col)), Some(line)) = (self.position, &self.line) {
+            let width = line_nb.to_string().len();
+            writeln!(f)?;
+            writeln!(f, "{:width$} |", "")?;
+            writeln!(f, "{line_nb} | {line}")?;
+            write!(f, "{:width$} | {:>col$}", "", "^")?;
+        }
+
+        if let Some(suggestion) = &self.suggestion {
+            writeln!(f)?;
+            write!(f, "help: did you mean `{suggestion}`?")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the message, position and unknown name of a RON error.
+fn ron_error(err: SpannedError) -> (String, Option<(usize, usize)>, Option<Unknown>) {
+    let position = Some((err.position.line, err.position.col));
+    let unknown = match &err.code {
+        RonError::NoSuchEnumVariant {
+            expected, found, ..
+        }
+        | RonError::NoSuchStructField {
+            expected, found, ..
+        } => Some((
+            found.clone(),
+            expected.iter().map(|s| s.to_string()).collect(),
+        )),
+        _ => None,
+    };
+    (err.code.to_string(), position, unknown)
+}
+
+/// Removes the position some errors add at the end of their message, it is
+/// already shown.
+fn strip_position(message: String, position: Option<(usize, usize)>) -> String {
+    let Some((line, col)) = position else {
+        return message;
+    };
+
+    let suffix = format!(" at line {line} column {col}");
+    match message.strip_suffix(&suffix) {
+        Some(stripped) => stripped.to_string(),
+        None => message,
+    }
+}
+
+/// Removes the snippet some parsers render in their message, e.g TOML, the
+/// snippet is shown once by [`ParseError`] itself.
+fn without_snippet(message: &str) -> String {
+    message
+        .lines()
+        .filter(|line| {
+            let line = line.trim_start_matches(|c: char| c.is_ascii_digit() || c == ' ');
+            !line.starts_with('|')
+        })
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Column, starting at 1, of the unknown name `found` in the line, rather
+/// than where the parser stopped at `col`.
+///
+/// The last occurrence up to `col` is preferred, then the first one after it.
+fn caret_column(line: &str, col: usize, found: &str) -> usize {
+    let end = line
+        .char_indices()
+        .nth(col)
+        .map_or(line.len(), |(idx, _)| idx);
+    line[..end]
+        .rfind(found)
+        .or_else(|| line[end..].find(found).map(|idx| end + idx))
+        .map_or(col, |idx| line[..idx].chars().count() + 1)
+}
+
+/// Line and column, starting at 1, of the byte `offset` in `buf`.
+fn position_of(buf: &str, offset: usize) -> (usize, usize) {
+    let before = &buf[..offset.min(buf.len())];
+    let line = before.matches('\n').count() + 1;
+    let col = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    (line, col)
+}
+
+/// Extracts the unknown name and the expected ones from the messages of
+/// serde, e.g "unknown variant `Cmdd`, expected one of `Cmd`, `TmuxVSplit`".
+fn unknown_name(message: &str) -> Option<Unknown> {
+    if !message.contains("unknown variant") && !message.contains("unknown field") {
+        return None;
+    }
+
+    // the quoted names are between every other backtick.
+    let mut names = message.split('`').skip(1).step_by(2).map(String::from);
+    let found = names.next()?;
+    Some((found, names.collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{Config, SpaceTree},
+        format::Format,
+    };
+
+    const CONFIG: &str = r#"(
+    version: 1,
+    default_tree: "jump",
+    trees: {
+        "jump": Cmdd("x"),
+    },
+)"#;
+
+    fn parse_error(file: &str, buf: &str, err: DsError) -> ParseError {
+        match ParseError::from_error(Path::new(file), buf, err) {
+            DsError::ParseError(err) => *err,
+            err => panic!("not a parse error: {err}"),
+        }
+    }
+
+    #[test]
+    fn position_in_buffer() {
+        let buf = "ab\ncdé\nf";
+        assert_eq!(position_of(buf, 0), (1, 1));
+        assert_eq!(position_of(buf, 1), (1, 2));
+        assert_eq!(position_of(buf, 3), (2, 1));
+        // columns count characters, not bytes.
+        assert_eq!(position_of(buf, buf.find('f').unwrap()), (3, 1));
+        assert_eq!(position_of(buf, buf.find('\n').unwrap() + 3), (2, 3));
+        assert_eq!(position_of(buf, buf.len() + 10), (3, 2));
+    }
+
+    #[test]
+    fn caret_on_unknown_name() {
+        let line = r#"        "jump": Cmdd("x"),"#;
+        // the parser stopped after the name.
+        assert_eq!(caret_column(line, 21, "Cmdd"), 17);
+        // the parser stopped before the name.
+        assert_eq!(caret_column("  jump: !Cmdd x", 3, "Cmdd"), 10);
+        assert_eq!(caret_column("  jump: x", 3, "Cmdd"), 3);
+    }
+
+    #[test]
+    fn ron_error_points_at_the_name() {
+        let err = Format::Ron.parse::<Config>(CONFIG).unwrap_err();
+        let err = parse_error("config.ron", CONFIG, err);
+
+        assert_eq!(err.position, Some((5, 17)));
+        assert_eq!(err.line.as_deref(), Some(r#"        "jump": Cmdd("x"),"#));
+        assert_eq!(err.suggestion.as_deref(), Some("Cmd"));
+    }
+
+    #[test]
+    fn transposed_field_is_suggested() {
+        let buf = r#"TmuxVSplit(lsh: Some(Cmd("hx")))"#;
+        let err = Format::Ron.parse::<SpaceTree>(buf).unwrap_err();
+        let err = parse_error("config.ron", buf, err);
+
+        assert_eq!(err.position, Some((1, 12)));
+        assert_eq!(err.suggestion.as_deref(), Some("lhs"));
+    }
+
+    #[test]
+    fn snippet_is_shown_once() {
+        let buf = "version = 1\ndefault_tree = \"jump\"\n[trees]\njump = { Cmdd = \"x\" }\n";
+        let err = Format::Toml.parse::<Config>(buf).unwrap_err();
+        let err = parse_error("config.toml", buf, err).to_string();
+
+        assert_eq!(err.matches("4 | jump").count(), 1, "{err}");
+        assert_eq!(err.matches('^').count(), 1, "{err}");
+        assert_eq!(
+            err,
+            "failed to parse config.toml:4:10, unknown variant `Cmdd`, expected one of \
+             `Cmd`, `TmuxVSplit`, `TmuxHSplit`\n  |\n4 | jump = { Cmdd = \"x\" }\n  |          ^\n\
+             help: did you mean `Cmd`?"
+        );
+    }
+
+    #[test]
+    fn parser_snippets_are_removed() {
+        let message =
+            "TOML parse error at line 1, column 5\n  |\n1 | a = \n  |     ^\nexpected a value";
+        assert_eq!(
+            without_snippet(message),
+            "TOML parse error at line 1, column 5 expected a value"
+        );
+        assert_eq!(without_snippet("expected comma"), "expected comma");
+    }
+
+    #[test]
+    fn other_errors_are_untouched() {
+        let err = ParseError::from_error(Path::new("db.ron"), "", DsError::InvalidREPL);
+        assert!(matches!(err, DsError::InvalidREPL));
+    }
+}
@@ -0,0 +1,141 @@
+//! Env files, `.env` like files whose variables are set in the environment of
+//! the session of a Space, so every pane inherits them.
+//!
+//! The env files of the tree of the Space are loaded first, then the ones of
+//! the Space, a variable set by several files takes the last value. Like the
+//! hooks, the tree is the `tree` of the Space even when a trusted project file
+//! replaces it.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use tmux_interface::TmuxCommand;
+
+use crate::{Context, DsError, Result};
+
+/// The variables of the env files of the Space.
+///
+/// The files are relative to the working directory of the Space, the ones
+/// that don't exist are skipped, like an optional `.env.local`.
+pub fn load(ctx: &Context, space_name: &str) -> Result<BTreeMap<String, String>> {
+    let space = ctx.db.get_space(space_name)?;
+    let tree_files = ctx
+        .config
+        .env_files
+        .get(&space.tree)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    load_files(&space.wdir, tree_files.iter().chain(&space.env_files))
+}
+
+/// The variables of the env files, relative to `wdir`, in order.
+fn load_files<'a>(
+    wdir: &Path,
+    files: impl IntoIterator<Item = &'a PathBuf>,
+) -> Result<BTreeMap<String, String>> {
+    let mut vars = BTreeMap::new();
+    for file in files {
+        let path = wdir.join(file);
+        if !path.is_file() {
+            continue;
+        }
+
+        let cant_load = |err: dotenvy::Error| DsError::CantLoadEnv(path.clone(), err.to_string());
+        for var in dotenvy::from_path_iter(&path).map_err(cant_load)? {
+            let (key, value) = var.map_err(cant_load)?;
+            vars.insert(key, value);
+        }
+    }
+
+    Ok(vars)
+}
+
+/// The variables with their values hidden, they may be secrets.
+pub fn redact(vars: BTreeMap<String, String>) -> BTreeMap<String, String> {
+    vars.into_keys()
+        .map(|key| (key, String::from("…")))
+        .collect()
+}
+
+/// Adds the variables to the environment of the session or window created by
+/// the command, `new-session` or `new-window`.
+pub fn with_vars<'a>(mut cmd: TmuxCommand<'a>, vars: &BTreeMap<String, String>) -> TmuxCommand<'a> {
+    for (key, value) in vars {
+        cmd.push_option("-e", format!("{key}={value}"));
+    }
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, process};
+
+    use super::*;
+
+    /// A fresh directory with the given files.
+    fn dir_with(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("devspace-env-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, content) in files {
+            fs::write(dir.join(file), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn later_files_override() {
+        let dir = dir_with(
+            "override",
+            &[
+                (".env", "# comment\nPORT=3000\nexport HOST=localhost\n"),
+                (".env.local", "PORT='8080'\nNAME=\"my app\"\n"),
+            ],
+        );
+        let files = [PathBuf::from(".env"), PathBuf::from(".env.local")];
+        let vars = load_files(&dir, &files).unwrap();
+
+        assert_eq!(
+            vars,
+            BTreeMap::from([
+                (String::from("HOST"), String::from("localhost")),
+                (String::from("NAME"), String::from("my app")),
+                (String::from("PORT"), String::from("8080")),
+            ])
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_files_are_skipped() {
+        let dir = dir_with("missing", &[(".env", "A=1\n")]);
+        let files = [PathBuf::from(".env.local"), PathBuf::from(".env")];
+        let vars = load_files(&dir, &files).unwrap();
+
+        assert_eq!(
+            vars,
+            BTreeMap::from([(String::from("A"), String::from("1"))])
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn malformed_file_is_an_error() {
+        let dir = dir_with("malformed", &[(".env", "A=\"unclosed\n")]);
+        let files = [PathBuf::from(".env")];
+        let err = load_files(&dir, &files).unwrap_err();
+
+        assert!(matches!(err, DsError::CantLoadEnv(path, _) if path == dir.join(".env")));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn redacted_values() {
+        let vars = BTreeMap::from([(String::from("TOKEN"), String::from("secret"))]);
+        let redacted = redact(vars);
+        assert_eq!(redacted["TOKEN"], "…");
+    }
+}
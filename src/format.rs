@@ -0,0 +1,107 @@
+//! The file formats the database and the config can be written in.
+
+use std::{
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{DsError, Result, diagnostics::ParseError, utils};
+
+/// A file format, detected from the extension of the file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    #[default]
+    Ron,
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Ron, Format::Toml, Format::Yaml, Format::Json];
+
+    /// The extension of the files in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Ron => "ron",
+            Format::Toml => "toml",
+            Format::Yaml => "yaml",
+            Format::Json => "json",
+        }
+    }
+
+    /// The format of the file, from its extension.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let ext = path.extension()?;
+        Format::ALL.into_iter().find(|f| ext == f.extension())
+    }
+
+    /// Path of the file named `stem` in `dir`, e.g `dir/config.toml`.
+    pub fn file_path(self, dir: &Path, stem: &str) -> PathBuf {
+        dir.join(format!("{stem}.{}", self.extension()))
+    }
+
+    /// Finds in which format the file named `stem` in `dir` is written.
+    ///
+    /// Defaults to RON if there is no such file, and fails if there are
+    /// several.
+    pub fn detect(dir: &Path, stem: &str) -> Result<Format> {
+        let found = Format::ALL
+            .into_iter()
+            .filter(|f| f.file_path(dir, stem).exists())
+            .collect::<Vec<_>>();
+
+        match found[..] {
+            [] => Ok(Format::default()),
+            [format] => Ok(format),
+            _ => Err(DsError::SeveralFiles(
+                found.iter().map(|f| f.file_path(dir, stem)).collect(),
+            )),
+        }
+    }
+
+    /// Deserializes the data from the buffer written in this format.
+    pub fn parse<T>(self, buf: &str) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(match self {
+            Format::Ron => ron::from_str(buf)?,
+            Format::Toml => toml::from_str(buf)?,
+            Format::Yaml => serde_yaml::from_str(buf)?,
+            Format::Json => serde_json::from_str(buf)?,
+        })
+    }
+
+    /// Deserializes the content of `file`, errors name the file and show
+    /// where it failed.
+    pub fn parse_file<T>(self, file: &Path, buf: &str) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        self.parse(buf)
+            .map_err(|err| ParseError::from_error(file, buf, err))
+    }
+
+    /// Serializes the data in this format, pretty printed.
+    pub fn serialize<T>(self, data: &T) -> Result<String>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(match self {
+            Format::Ron => ron::ser::to_string_pretty(data, utils::pretty_printer_config())?,
+            Format::Toml => toml::to_string_pretty(data)?,
+            Format::Yaml => serde_yaml::to_string(data)?,
+            Format::Json => serde_json::to_string_pretty(data)?,
+        })
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
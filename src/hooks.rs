@@ -0,0 +1,46 @@
+//! Hooks, commands run outside of the panes around the life of a Space.
+//!
+//! The hooks of the tree of the Space run first, then the ones of the Space.
+//! The tree is the `tree` of the Space, even when the one of a trusted project
+//! file is launched instead, the project file has no name to key hooks on.
+
+use std::process::Command;
+
+use crate::{
+    Context, DsError, Result,
+    config::{Hook, cmd_placeholders},
+};
+
+/// Runs the hooks of the Space for the given moment, in its working
+/// directory.
+///
+/// Stops at the first hook that fails.
+pub fn run(ctx: &Context, space_name: &str, hook: Hook) -> Result {
+    let space = ctx.db.get_space(space_name)?;
+    let tree_hook = ctx
+        .config
+        .hooks
+        .get(&space.tree)
+        .and_then(|hooks| hooks.get(hook));
+    let space_hook = space.hooks.get(hook);
+
+    for cmd in [tree_hook, space_hook].into_iter().flatten() {
+        let cmd = cmd_placeholders(cmd, space)?;
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(&cmd)
+            .current_dir(&space.wdir)
+            .status()?;
+
+        if !status.success() {
+            return Err(DsError::HookFailed {
+                space: space_name.to_string(),
+                hook: hook.name(),
+                cmd,
+                code: status.code(),
+            });
+        }
+    }
+
+    Ok(())
+}
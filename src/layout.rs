@@ -0,0 +1,576 @@
+//! tmux layouts, the geometry of the panes of a window as shown by
+//! `#{window_layout}` and used by `select-layout`, e.g
+//! `1af1,200x50,0,0{99x50,0,0,0,100x50,100,0,1}`.
+
+use crate::config::SpaceTree;
+
+/// Size of the window the named layouts are laid out in, large enough for
+/// the sizes of their splits to be exact percentages.
+const NAMED_SIZE: (usize, usize) = (1000, 1000);
+
+/// A cell of a layout, with its size and position.
+#[derive(Debug, Clone)]
+pub struct Cell<T> {
+    pub sx: usize,
+    pub sy: usize,
+    pub x: usize,
+    pub y: usize,
+    pub kind: CellKind<T>,
+}
+
+#[derive(Debug, Clone)]
+pub enum CellKind<T> {
+    /// A pane.
+    Pane(T),
+    /// Cells side by side, from left to right.
+    Row(Vec<Cell<T>>),
+    /// Cells stacked, from top to bottom.
+    Column(Vec<Cell<T>>),
+}
+
+impl<'a> Cell<Option<&'a str>> {
+    /// The cell of the tree in a window of the given size, splits are split in
+    /// two like `go` does. Panes hold their command, if any.
+    pub fn from_tree(
+        tree: Option<&'a SpaceTree>,
+        sx: usize,
+        sy: usize,
+        x: usize,
+        y: usize,
+    ) -> Self {
+        let kind = match tree {
+            None => CellKind::Pane(None),
+            Some(SpaceTree::Cmd(cmd)) => CellKind::Pane(Some(cmd.as_str())),
+            Some(SpaceTree::TmuxVSplit { lhs, rhs, size }) => {
+                // one column is taken by the border between the panes.
+                let (lhs_sx, rhs_sx) = split_sizes(sx, *size);
+                let lhs = Cell::from_tree(lhs.as_deref(), lhs_sx, sy, x, y);
+                let rhs = Cell::from_tree(rhs.as_deref(), rhs_sx, sy, x + lhs_sx + 1, y);
+                CellKind::Row(
+                    lhs.flatten_row()
+                        .into_iter()
+                        .chain(rhs.flatten_row())
+                        .collect(),
+                )
+            }
+            Some(SpaceTree::TmuxHSplit { top, bottom, size }) => {
+                let (top_sy, bottom_sy) = split_sizes(sy, *size);
+                let top = Cell::from_tree(top.as_deref(), sx, top_sy, x, y);
+                let bottom = Cell::from_tree(bottom.as_deref(), sx, bottom_sy, x, y + top_sy + 1);
+                CellKind::Column(
+                    top.flatten_column()
+                        .into_iter()
+                        .chain(bottom.flatten_column())
+                        .collect(),
+                )
+            }
+        };
+
+        Cell { sx, sy, x, y, kind }
+    }
+}
+
+impl Cell<usize> {
+    /// Parses a layout, panes hold their id, the number of `%1`.
+    ///
+    /// Returns `None` if the layout is malformed.
+    pub fn parse(layout: &str) -> Option<Self> {
+        // skip the checksum.
+        let (_, layout) = layout.split_once(',')?;
+        let mut parser = Parser { rest: layout };
+        let cell = parser.cell()?;
+        parser.rest.is_empty().then_some(cell)
+    }
+
+    /// The same cell with the panes holding their index in the layout,
+    /// starting at 0, the order tmux gives them their place.
+    pub fn indexed(self) -> Self {
+        let mut idx = 0;
+        self.map(&mut |_| {
+            idx += 1;
+            idx - 1
+        })
+    }
+
+    /// The cells of one of the layouts built in tmux, like `main-vertical`,
+    /// with the given number of panes. The cells of a row or a column share
+    /// it evenly, the main pane of `main-*` takes half of the window.
+    ///
+    /// Returns `None` if the layout isn't known.
+    pub fn named(name: &str, panes: usize) -> Option<Self> {
+        let pane = |idx| Cell::without_size(CellKind::Pane(idx));
+        let all = |range: std::ops::Range<usize>| range.map(pane).collect::<Vec<_>>();
+        let group = |cells: Vec<Cell<usize>>, kind: fn(Vec<Cell<usize>>) -> CellKind<usize>| {
+            if cells.len() == 1 {
+                cells.into_iter().next().expect("one cell")
+            } else {
+                Cell::without_size(kind(cells))
+            }
+        };
+
+        let cell = match name {
+            _ if panes <= 1 => pane(0),
+            "even-horizontal" => group(all(0..panes), CellKind::Row),
+            "even-vertical" => group(all(0..panes), CellKind::Column),
+            "main-vertical" => group(
+                vec![pane(0), group(all(1..panes), CellKind::Column)],
+                CellKind::Row,
+            ),
+            "main-horizontal" => group(
+                vec![pane(0), group(all(1..panes), CellKind::Row)],
+                CellKind::Column,
+            ),
+            "tiled" => {
+                let cols = (1..).find(|cols| cols * cols >= panes).unwrap_or(1);
+                let rows = (0..panes)
+                    .step_by(cols)
+                    .map(|start| group(all(start..(start + cols).min(panes)), CellKind::Row))
+                    .collect();
+                group(rows, CellKind::Column)
+            }
+            _ => return None,
+        };
+        Some(cell.shared(NAMED_SIZE.0, NAMED_SIZE.1, 0, 0))
+    }
+
+    /// The same cell with the given size and position, shared evenly by its
+    /// cells.
+    fn shared(self, sx: usize, sy: usize, x: usize, y: usize) -> Self {
+        let kind = match self.kind {
+            CellKind::Pane(pane) => CellKind::Pane(pane),
+            CellKind::Row(cells) => {
+                let mut x = x;
+                let extents = shares(sx, cells.len());
+                let cells = cells.into_iter().zip(extents).map(|(cell, sx)| {
+                    let cell = cell.shared(sx, sy, x, y);
+                    x += sx + 1;
+                    cell
+                });
+                CellKind::Row(cells.collect())
+            }
+            CellKind::Column(cells) => {
+                let mut y = y;
+                let extents = shares(sy, cells.len());
+                let cells = cells.into_iter().zip(extents).map(|(cell, sy)| {
+                    let cell = cell.shared(sx, sy, x, y);
+                    y += sy + 1;
+                    cell
+                });
+                CellKind::Column(cells.collect())
+            }
+        };
+        Cell { sx, sy, x, y, kind }
+    }
+
+    fn without_size(kind: CellKind<usize>) -> Self {
+        Cell {
+            sx: 0,
+            sy: 0,
+            x: 0,
+            y: 0,
+            kind,
+        }
+    }
+}
+
+impl<T> Cell<T> {
+    // tmux never nests cells of the same direction, their cells are merged.
+    fn flatten_row(self) -> Vec<Self> {
+        match self.kind {
+            CellKind::Row(cells) => cells,
+            _ => vec![self],
+        }
+    }
+
+    fn flatten_column(self) -> Vec<Self> {
+        match self.kind {
+            CellKind::Column(cells) => cells,
+            _ => vec![self],
+        }
+    }
+
+    /// The same cell with `f` applied on every pane, in the order of the
+    /// layout.
+    pub fn map<U>(self, f: &mut impl FnMut(T) -> U) -> Cell<U> {
+        let map = |cells: Vec<Cell<T>>, f: &mut _| cells.into_iter().map(|c| c.map(f)).collect();
+        let kind = match self.kind {
+            CellKind::Pane(pane) => CellKind::Pane(f(pane)),
+            CellKind::Row(cells) => CellKind::Row(map(cells, f)),
+            CellKind::Column(cells) => CellKind::Column(map(cells, f)),
+        };
+        Cell {
+            sx: self.sx,
+            sy: self.sy,
+            x: self.x,
+            y: self.y,
+            kind,
+        }
+    }
+
+    /// The panes, in the order of the layout.
+    pub fn panes(&self) -> Vec<&T> {
+        let mut panes = Vec::new();
+        self.push_panes(&mut panes);
+        panes
+    }
+
+    fn push_panes<'a>(&'a self, panes: &mut Vec<&'a T>) {
+        match &self.kind {
+            CellKind::Pane(pane) => panes.push(pane),
+            CellKind::Row(cells) | CellKind::Column(cells) => {
+                cells.iter().for_each(|cell| cell.push_panes(panes))
+            }
+        }
+    }
+
+    /// The layout string of the cell, with its checksum.
+    pub fn layout(&self) -> String {
+        let mut layout = String::new();
+        self.write_layout(&mut layout, &mut 0);
+        format!("{:04x},{layout}", checksum(&layout))
+    }
+
+    fn write_layout(&self, out: &mut String, pane_idx: &mut usize) {
+        out.push_str(&format!("{}x{},{},{}", self.sx, self.sy, self.x, self.y));
+        let (cells, open, close) = match &self.kind {
+            CellKind::Pane(_) => {
+                out.push_str(&format!(",{pane_idx}"));
+                *pane_idx += 1;
+                return;
+            }
+            CellKind::Row(cells) => (cells, '{', '}'),
+            CellKind::Column(cells) => (cells, '[', ']'),
+        };
+
+        out.push(open);
+        for (i, cell) in cells.iter().enumerate() {
+            if i != 0 {
+                out.push(',');
+            }
+            cell.write_layout(out, pane_idx);
+        }
+        out.push(close);
+    }
+
+    /// Converts the cell into a tree, `leaf` gives the tree of every pane.
+    ///
+    /// Cells of more than two panes become nested splits, e.g a row of three
+    /// panes is a `TmuxVSplit` whose right side is a `TmuxVSplit`.
+    pub fn into_tree(self, leaf: &mut impl FnMut(T) -> Option<SpaceTree>) -> Option<SpaceTree> {
+        match self.kind {
+            CellKind::Pane(pane) => leaf(pane),
+            CellKind::Row(cells) => Some(split(
+                cells,
+                leaf,
+                |cell| cell.sx,
+                |lhs, rhs, size| SpaceTree::TmuxVSplit { lhs, rhs, size },
+            )),
+            CellKind::Column(cells) => Some(split(
+                cells,
+                leaf,
+                |cell| cell.sy,
+                |top, bottom, size| SpaceTree::TmuxHSplit { top, bottom, size },
+            )),
+        }
+    }
+}
+
+type Side = Option<Box<SpaceTree>>;
+
+/// Splits the cells in two, the first one and the others.
+///
+/// `extent` is the size of a cell in the direction of the split, the split
+/// gets a size if the first cell isn't about half of the cells.
+fn split<T>(
+    cells: Vec<Cell<T>>,
+    leaf: &mut impl FnMut(T) -> Option<SpaceTree>,
+    extent: fn(&Cell<T>) -> usize,
+    new: fn(Side, Side, Option<u8>) -> SpaceTree,
+) -> SpaceTree {
+    // every cell but the last one is followed by a border.
+    let total = cells
+        .iter()
+        .map(|cell| extent(cell) + 1)
+        .sum::<usize>()
+        .saturating_sub(1);
+    let first_extent = cells.first().map_or(0, extent);
+    let size = (first_extent > 0 && first_extent.abs_diff(halves(total).0) > 1)
+        .then(|| (first_extent * 100 + total / 2) / total)
+        .map(|size| size.clamp(1, 99) as u8);
+
+    let mut cells = cells.into_iter();
+    let first = cells.next().and_then(|cell| cell.into_tree(leaf));
+    let others = cells.collect::<Vec<_>>();
+
+    let others = match others.len() {
+        0 => None,
+        1 => others
+            .into_iter()
+            .next()
+            .and_then(|cell| cell.into_tree(leaf)),
+        _ => Some(split(others, leaf, extent, new)),
+    };
+    new(first.map(Box::new), others.map(Box::new), size)
+}
+
+/// The extents of `count` cells sharing `extent` evenly, the borders between
+/// them excluded. The last one gets what is left.
+fn shares(extent: usize, count: usize) -> Vec<usize> {
+    let borders = count.saturating_sub(1);
+    let share = extent.saturating_sub(borders) / count.max(1);
+    let mut shares = vec![share; count];
+    if let Some(last) = shares.last_mut() {
+        *last = extent.saturating_sub((share + 1) * borders);
+    }
+    shares
+}
+
+/// The sizes of the two panes tmux splits a pane of the given size in, the
+/// border between them excluded.
+fn halves(size: usize) -> (usize, usize) {
+    let second = size.div_ceil(2).saturating_sub(1);
+    (size.saturating_sub(second + 1), second)
+}
+
+/// The sizes of the two panes of a split with the given size, see
+/// [`halves`] for a split without size.
+fn split_sizes(size: usize, percentage: Option<u8>) -> (usize, usize) {
+    match percentage {
+        Some(percentage) => {
+            let second = size * 100usize.saturating_sub(percentage as usize) / 100;
+            (size.saturating_sub(second + 1), second)
+        }
+        None => halves(size),
+    }
+}
+
+/// The checksum tmux prefixes layouts with.
+fn checksum(layout: &str) -> u16 {
+    layout.bytes().fold(0u16, |csum, byte| {
+        ((csum >> 1) | ((csum & 1) << 15)).wrapping_add(byte as u16)
+    })
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl Parser<'_> {
+    /// Parses `WxH,X,Y` followed by `,ID`, `{cells}` or `[cells]`.
+    fn cell(&mut self) -> Option<Cell<usize>> {
+        let sx = self.number()?;
+        self.eat('x')?;
+        let sy = self.number()?;
+        self.eat(',')?;
+        let x = self.number()?;
+        self.eat(',')?;
+        let y = self.number()?;
+
+        let kind = if self.eat('{').is_some() {
+            CellKind::Row(self.cells('}')?)
+        } else if self.eat('[').is_some() {
+            CellKind::Column(self.cells(']')?)
+        } else {
+            self.eat(',')?;
+            CellKind::Pane(self.number()?)
+        };
+
+        Some(Cell { sx, sy, x, y, kind })
+    }
+
+    fn cells(&mut self, close: char) -> Option<Vec<Cell<usize>>> {
+        let mut cells = vec![self.cell()?];
+        while self.eat(close).is_none() {
+            self.eat(',')?;
+            cells.push(self.cell()?);
+        }
+        Some(cells)
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let end = self
+            .rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest.len());
+        let number = self.rest[..end].parse().ok()?;
+        self.rest = &self.rest[end..];
+        Some(number)
+    }
+
+    fn eat(&mut self, c: char) -> Option<()> {
+        self.rest = self.rest.strip_prefix(c)?;
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cmd(cmd: &str) -> Option<Box<SpaceTree>> {
+        Some(Box::new(SpaceTree::Cmd(String::from(cmd))))
+    }
+
+    #[test]
+    fn checksum_matches_tmux() {
+        // checksums of layouts printed by tmux.
+        assert_eq!(checksum("159x48,0,0{79x48,0,0,79x48,80,0}"), 0xbb62);
+    }
+
+    #[test]
+    fn parse_layout() {
+        let cell = Cell::parse(
+            "c1b5,200x50,0,0{99x50,0,0,3,100x50,100,0[100x25,100,0,4,100x24,100,26,5]}",
+        )
+        .expect("valid layout");
+        assert_eq!((cell.sx, cell.sy, cell.x, cell.y), (200, 50, 0, 0));
+        assert_eq!(cell.panes(), vec![&3, &4, &5]);
+
+        let CellKind::Row(cells) = &cell.kind else {
+            panic!("expected a row, got {:?}", cell.kind);
+        };
+        assert_eq!((cells[1].sx, cells[1].x), (100, 100));
+        assert!(matches!(cells[1].kind, CellKind::Column(_)));
+    }
+
+    #[test]
+    fn parse_malformed_layout() {
+        assert!(Cell::parse("").is_none());
+        assert!(Cell::parse("c1b5,200x50,0,0").is_none());
+        assert!(Cell::parse("c1b5,200x50,0,0{99x50,0,0,3").is_none());
+        assert!(Cell::parse("c1b5,200x50,0,0,1 trailing").is_none());
+    }
+
+    #[test]
+    fn layout_round_trips() {
+        let layout = "200x50,0,0{99x50,0,0,0,100x50,100,0[100x25,100,0,1,100x24,100,26,2]}";
+        let with_checksum = format!("{:04x},{layout}", checksum(layout));
+        let cell = Cell::parse(&with_checksum).expect("valid layout");
+        assert_eq!(cell.layout(), with_checksum);
+    }
+
+    #[test]
+    fn indexed_panes() {
+        let cell = Cell::parse("0000,200x50,0,0{99x50,0,0,7,100x50,100,0,2}").expect("valid");
+        assert_eq!(cell.indexed().panes(), vec![&0, &1]);
+    }
+
+    #[test]
+    fn named_layouts() {
+        assert_eq!(Cell::named("tiled", 0).expect("known").panes(), vec![&0]);
+        assert_eq!(
+            Cell::named("even-vertical", 1).expect("known").panes(),
+            vec![&0]
+        );
+        assert!(Cell::named("unknown", 3).is_none());
+
+        let even = Cell::named("even-horizontal", 3).expect("known");
+        assert!(matches!(&even.kind, CellKind::Row(cells) if cells.len() == 3));
+
+        let main = Cell::named("main-vertical", 3).expect("known");
+        let CellKind::Row(cells) = &main.kind else {
+            panic!("expected a row, got {:?}", main.kind);
+        };
+        assert!(matches!(cells[0].kind, CellKind::Pane(0)));
+        assert!(matches!(&cells[1].kind, CellKind::Column(cells) if cells.len() == 2));
+
+        // 5 panes are tiled in rows of 3 and 2.
+        let tiled = Cell::named("tiled", 5).expect("known");
+        let CellKind::Column(rows) = &tiled.kind else {
+            panic!("expected a column, got {:?}", tiled.kind);
+        };
+        assert_eq!(
+            rows.iter().map(|row| row.panes().len()).collect::<Vec<_>>(),
+            vec![3, 2]
+        );
+        assert_eq!(tiled.panes(), vec![&0, &1, &2, &3, &4]);
+    }
+
+    #[test]
+    fn even_layouts_are_even() {
+        let leaf = &mut |idx: usize| Some(SpaceTree::Cmd(idx.to_string()));
+        let pane = |idx: usize| cmd(&idx.to_string());
+
+        let tree = Cell::named("even-horizontal", 3)
+            .expect("known")
+            .into_tree(leaf);
+        assert_eq!(
+            tree,
+            Some(SpaceTree::TmuxVSplit {
+                lhs: pane(0),
+                rhs: Some(Box::new(SpaceTree::TmuxVSplit {
+                    lhs: pane(1),
+                    rhs: pane(2),
+                    size: None,
+                })),
+                size: Some(33),
+            })
+        );
+
+        let tree = Cell::named("even-vertical", 4)
+            .expect("known")
+            .into_tree(leaf);
+        let Some(SpaceTree::TmuxHSplit {
+            bottom: Some(bottom),
+            size: Some(25),
+            ..
+        }) = tree
+        else {
+            panic!("expected a quarter on top, got {tree:?}");
+        };
+        assert!(matches!(
+            *bottom,
+            SpaceTree::TmuxHSplit { size: Some(33), .. }
+        ));
+
+        // the main pane takes half of the window, the others share the rest.
+        let tree = Cell::named("main-vertical", 4)
+            .expect("known")
+            .into_tree(leaf);
+        let Some(SpaceTree::TmuxVSplit {
+            rhs: Some(rhs),
+            size: None,
+            ..
+        }) = tree
+        else {
+            panic!("expected halves, got {tree:?}");
+        };
+        assert!(matches!(*rhs, SpaceTree::TmuxHSplit { size: Some(33), .. }));
+    }
+
+    #[test]
+    fn shares_of_cells() {
+        assert_eq!(shares(100, 1), vec![100]);
+        assert_eq!(shares(100, 2), vec![49, 50]);
+        assert_eq!(shares(1000, 3), vec![332, 332, 334]);
+        assert!(shares(100, 0).is_empty());
+    }
+
+    #[test]
+    fn sizes_of_splits() {
+        assert_eq!(halves(100), (50, 49));
+        assert_eq!(halves(101), (50, 50));
+        assert_eq!(split_sizes(100, None), halves(100));
+        assert_eq!(split_sizes(100, Some(70)), (69, 30));
+        assert_eq!(split_sizes(100, Some(0)), (0, 100));
+    }
+
+    #[test]
+    fn tree_round_trips() {
+        let tree = SpaceTree::TmuxVSplit {
+            lhs: cmd("hx"),
+            rhs: Some(Box::new(SpaceTree::TmuxHSplit {
+                top: cmd("cargo watch"),
+                bottom: None,
+                size: None,
+            })),
+            size: Some(70),
+        };
+
+        let cell = Cell::from_tree(Some(&tree), 200, 50, 0, 0);
+        assert_eq!(cell.panes(), vec![&Some("hx"), &Some("cargo watch"), &None]);
+
+        let tree_again = cell.into_tree(&mut |pane| pane.map(|cmd| SpaceTree::Cmd(cmd.into())));
+        assert_eq!(tree_again, Some(tree));
+    }
+}
@@ -4,42 +4,59 @@
 // TODO: make groups of command, like Spaces related commands, Trees related
 // commands etc
 //
-// TODO: save the last used Space and put it in some file like the config so
-// you aren't forced to put the name of the Space everywhere if there is
-// already one stored.
-//
 // TODO: add a thing that checks if a new version is available and a config
 // param to disable it. If a new version is available, print a warn when using
 // the app.
-//
-// TODO: make the shell completions with `clap_complete`
 use std::{
     env::{VarError, var},
     fmt::{Debug, Error as FmtError},
-    fs::{File, create_dir_all, read_to_string},
+    fs::{File, create_dir_all, read_to_string, rename},
     io::Write,
     panic::{self, AssertUnwindSafe},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use clap::{CommandFactory, FromArgMatches, Parser};
+use clap_complete::{ArgValueCandidates, CompleteEnv, Shell as CompletionShell};
 use ron::de::SpannedError;
 use shadow_rs::shadow;
 use thiserror::Error;
 use tmux_interface::Error as TmuxError;
 
 use crate::cmds::*;
+use crate::completions::COMPLETE_VAR;
 use crate::config::{CmdParsingError, Config, SpaceTreeId};
 use crate::database::DataBase;
+use crate::diagnostics::ParseError;
+use crate::edit::HookArgs;
+use crate::export_script::ScriptFormat;
+use crate::format::Format;
+use crate::group::GroupCommand;
+use crate::list_spaces::SpaceSort;
+use crate::migrations::Migration;
 use crate::new_tree::InteractiveError;
+use crate::shell_init::Shell;
 
 shadow!(build);
 pub(crate) mod cmds;
 pub mod config;
 pub mod database;
+pub mod diagnostics;
+pub mod env_file;
+pub mod format;
+pub mod hooks;
+pub mod layout;
+pub mod migrations;
+pub mod project;
 pub mod repl;
 pub mod utils;
 
+/// Name of the database file, without its extension.
+const DB_FILE: &str = "db";
+
+/// Name of the config file, without its extension.
+const CONF_FILE: &str = "config";
+
 const LONG_ABOUT: &str = "\
 Devspace is a tool to save and retrieve your devlopment workspaces.";
 
@@ -54,16 +71,54 @@ pub enum DsError {
     IoError(#[from] std::io::Error),
     #[error("failed to parse a file, {0}.")]
     FileParsingError(#[from] SpannedError),
+    #[error("{0}")]
+    ParseError(Box<ParseError>),
     #[error("failed to save the database: {0}")]
     DbSavingError(#[from] ron::Error),
+    #[error("failed to parse a TOML file, {0}")]
+    TomlParsingError(#[from] toml::de::Error),
+    #[error("failed to save a TOML file: {0}")]
+    TomlSavingError(#[from] toml::ser::Error),
+    #[error("YAML: {0}")]
+    YamlError(#[from] serde_yaml::Error),
+    #[error("JSON: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("several files found, keep only one of {0:?}.")]
+    SeveralFiles(Vec<PathBuf>),
     #[error("the space {0:?} was not found.")]
     SpaceNotFound(String),
+    #[error("no space given, and no space found for the current directory or last used.")]
+    NoSpaceGiven,
+    #[error("the directory {0:?} isn't in a space.")]
+    NotInSpace(PathBuf),
     #[error("the space {0:?} already exists.")]
     SpaceAlreadyExists(String),
     #[error("TMUX: {0}")]
     TmuxError(#[from] TmuxError),
     #[error("space treee {:?} not found", .0.0)]
     SpaceTreeNotFound(SpaceTreeId),
+    #[error(
+        "the default tree {tree:?} doesn't exist, create it or change `default_tree` in {1:?}.",
+        tree = .0.0
+    )]
+    UnknownDefaultTree(SpaceTreeId, PathBuf),
+    #[error(
+        "the space {0:?} uses the tree {tree:?} which doesn't exist, run `devspace doctor` or `devspace edit {0} --tree <TREE>` to fix it.",
+        tree = .1.0
+    )]
+    SpaceUsesUnknownTree(String, SpaceTreeId),
+    #[error("the tree {tree:?} is defined in both {1:?} and {2:?}.", tree = .0.0)]
+    DuplicateTree(SpaceTreeId, PathBuf, PathBuf),
+    #[error("can't read {1:?}, from the `include` list of {0:?}: {2}.")]
+    CantReadInclude(PathBuf, PathBuf, std::io::Error),
+    #[error("can't read the trees of {0:?}: {1}.")]
+    CantReadTrees(PathBuf, std::io::Error),
+    #[error("the tree {tree:?} comes from {1:?}, change it there.", tree = .0.0)]
+    IncludedTree(SpaceTreeId, PathBuf),
+    #[error("the tree {0:?} is still used by {1}, use `--reassign <TREE>` or `--force`.")]
+    TreeInUse(String, String),
+    #[error("the tree {0:?} is the default tree, use `--reassign <TREE>` to pick the new one.")]
+    DefaultTreeInUse(String),
     #[error("failed to parse command, {0}")]
     CmdParsingError(CmdParsingError),
     #[error("no space or tree to list.")]
@@ -78,6 +133,52 @@ pub enum DsError {
     DirDoesntExists(PathBuf),
     #[error(transparent)]
     InteractiveError(#[from] InteractiveError),
+    #[error("can't import {0:?}, {1}.")]
+    CantImport(PathBuf, String),
+    #[error("the tmux session {0:?} was not found.")]
+    SessionNotFound(String),
+    #[error("can't capture the tmux session {0:?}, {1}.")]
+    CantCapture(String, String),
+    #[error("can't stop the tmux session {0:?}, {1}.")]
+    CantStop(String, String),
+    #[error("the space {0:?} isn't running.")]
+    NotRunning(String),
+    #[error("tmux failed to go to the session {0:?}.")]
+    CantGo(String),
+    #[error("can't open a window for the space {0:?}, {1}.")]
+    CantOpenWindow(String, String),
+    #[error("not inside a tmux session.")]
+    NotInTmux,
+    #[error("can't reload the space {0:?}, {1}.")]
+    CantReload(String, String),
+    #[error("the group {0:?} was not found.")]
+    GroupNotFound(String),
+    #[error("the group {0:?} already exists.")]
+    GroupAlreadyExists(String),
+    #[error(
+        "the {hook} hook of the space {space:?} failed with {status}: {cmd}",
+        status = .code.map_or_else(|| String::from("a signal"), |code| format!("the code {code}"))
+    )]
+    HookFailed {
+        space: String,
+        hook: &'static str,
+        cmd: String,
+        code: Option<i32>,
+    },
+    #[error("{0} on_stop hook(s) failed.")]
+    HooksFailed(usize),
+    #[error("can't load the env file {0:?}, {1}.")]
+    CantLoadEnv(PathBuf, String),
+    #[error("unknown subcommand {0:?}.")]
+    UnknownSubcommand(String),
+    #[error("{0} issue(s) found.")]
+    IssuesFound(usize),
+    #[error("the file {file:?} has the version {version} but only up to {supported} is supported.")]
+    UnsupportedVersion {
+        file: PathBuf,
+        version: u32,
+        supported: u32,
+    },
 }
 
 #[derive(Parser, Debug)]
@@ -105,21 +206,41 @@ impl Cli {
             return Ok(d.to_path_buf());
         }
 
-        // then the var
+        Ok(Cli::default_dir())
+    }
+
+    /// Gets the directory where to put the devspace stuff when no `--dir` is
+    /// given.
+    pub fn default_dir() -> PathBuf {
+        // first the var
         if let Ok(dir) = var("DEVSPACE_DIR") {
-            return Ok(dir.into());
+            return dir.into();
         }
 
         // fallback to the default
         let mut default = PathBuf::from(var("HOME").expect("variable HOME not found wtf"));
         default.push(concat!(".", env!("CARGO_PKG_NAME"), "/"));
-        Ok(default)
+        default
+    }
+
+    /// Creates the Context the subcommand runs in.
+    ///
+    /// The trees referenced by the config and the database are validated,
+    /// except for the subcommands made to inspect or fix them.
+    pub fn context(&self) -> Result<Context> {
+        let dir = self.dir()?;
+        match &self.subcmds {
+            Some(cmd) if !cmd.repairs() => Context::new(dir),
+            _ => Context::load(dir),
+        }
     }
 }
 
 #[derive(Parser, Debug)]
 pub enum Command {
     /// Initializes a new development space.
+    ///
+    /// If the directory has a `.devspace.ron`, asks to trust it.
     Init {
         /// Base path of the new Space.
         #[arg(default_value = ".")]
@@ -127,46 +248,267 @@ pub enum Command {
         /// What treee of Space it is, how to launch it.
         ///
         /// Defaults to the default set in the config.
+        #[arg(add = ArgValueCandidates::new(completions::trees))]
         tree: Option<SpaceTreeId>,
+        /// A short description of the Space.
+        #[arg(long)]
+        description: Option<String>,
+        /// Tags of the Space, can be repeated.
+        #[arg(long = "tag")]
+        tags: Vec<String>,
     },
     /// Prints (to stdout) the working directory of a Space.
     Wdir {
         /// The space you want to goto.
-        space: String,
+        ///
+        /// Defaults to the Space containing the current directory, then to
+        /// the last used Space.
+        #[arg(add = ArgValueCandidates::new(completions::spaces))]
+        space: Option<String>,
     },
     /// Lists all the Spaces stored.
     ///
     /// Returns a non-zero exit code if there is no spaces stored.
     #[command(visible_alias = "ls")]
-    ListSpaces,
+    ListSpaces {
+        /// Only lists the Spaces with this tag, can be repeated.
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// How the Spaces are sorted.
+        #[arg(long, value_enum, default_value_t)]
+        sort: SpaceSort,
+    },
     /// Lists all the Trees configured.
     ///
     /// Returns a non-zero exit code if there is no spaces stored.
     #[command(visible_alias = "lt")]
     ListTrees,
+    /// Prints (to stdout) the name of the Space containing the current
+    /// directory.
+    ///
+    /// Returns a non-zero exit code if the current directory isn't in a Space.
+    Current {
+        /// Prints the working directory of the Space instead of its name.
+        #[arg(long)]
+        wdir: bool,
+    },
     /// Removes the Space with the given name.
     #[command(visible_alias = "rm-s")]
     RemoveSpace {
         /// Name of the Space to remove.
-        space: String,
+        ///
+        /// Defaults to the Space containing the current directory, then to
+        /// the last used Space, after asking for confirmation.
+        #[arg(add = ArgValueCandidates::new(completions::spaces))]
+        space: Option<String>,
     },
     /// Go to the Space with the given name.
     ///
-    /// If the Space has already been launched the Space isn't recreated.
+    /// If the Space has already been launched the Space isn't recreated. If
+    /// the working directory of the Space has a trusted `.devspace.ron`, its
+    /// tree is used instead of the tree of the Space.
+    ///
+    /// Inside tmux the client is switched to the session of the Space rather
+    /// than nesting it.
     Go {
         /// Name of the Space to go to.
-        space: String,
+        ///
+        /// Defaults to the Space containing the current directory, then to
+        /// the last used Space.
+        #[arg(add = ArgValueCandidates::new(completions::spaces))]
+        space: Option<String>,
+        /// Print the tmux commands that would be run as a shell script,
+        /// without contacting tmux.
+        #[arg(long)]
+        dry_run: bool,
+        /// Open the Space as a new window of the current tmux session.
+        #[arg(long)]
+        new_window: bool,
+        /// Apply the changes of the tree to the running session of the Space
+        /// before going to it.
+        ///
+        /// Missing panes are added and the panes whose command changed are
+        /// interrupted and get the new one, the other panes are untouched.
+        #[arg(long, conflicts_with_all = ["dry_run", "new_window"])]
+        reload: bool,
+        /// Go to every Space of the group, the first one is gone to and the
+        /// others are launched in the background.
+        #[arg(
+            long,
+            value_name = "GROUP",
+            conflicts_with_all = ["space", "dry_run", "new_window", "reload"],
+            add = ArgValueCandidates::new(completions::groups)
+        )]
+        group: Option<String>,
+        /// Launch the Spaces of the group as the windows of a single session.
+        #[arg(long, requires = "group")]
+        combined: bool,
+    },
+    /// Prints (to stdout) a standalone script launching a Space, for machines
+    /// without devspace.
+    ///
+    /// The script is a bash script calling tmux, or a tmuxinator or tmuxp
+    /// project file.
+    ExportScript {
+        /// Name of the Space to export.
+        ///
+        /// Defaults to the Space containing the current directory, then to
+        /// the last used Space.
+        #[arg(add = ArgValueCandidates::new(completions::spaces))]
+        space: Option<String>,
+        /// What the Space is exported as.
+        #[arg(long, value_enum, default_value_t)]
+        format: ScriptFormat,
+    },
+    /// Imports tmuxinator and tmuxp project files as trees.
+    ///
+    /// A project with several windows gives a tree per window, named after
+    /// the project and the window. What can't be converted is reported.
+    Import {
+        /// The project files to import.
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Also creates a Space for the root directory of every project.
+        #[arg(long)]
+        space: bool,
+    },
+    /// Captures the current window of a running tmux session as a tree.
+    ///
+    /// The panes keep their place and size, and run their current command in
+    /// their current directory.
+    Capture {
+        /// Name of the tmux session to capture.
+        session: String,
+        /// Name of the tree to save.
+        #[arg(long = "as", value_name = "TREE")]
+        tree: String,
+    },
+    /// Shows which Spaces are running, with their attached clients, windows,
+    /// panes and uptime.
+    ///
+    /// The tmux sessions of removed Spaces are listed too. Returns a non-zero
+    /// exit code if there is nothing to list.
+    #[command(visible_alias = "ps")]
+    Status {
+        /// Only lists the running Spaces.
+        #[arg(long)]
+        running: bool,
+    },
+    /// Stops a running Space, killing its tmux session.
+    #[command(visible_alias = "kill")]
+    Stop {
+        /// Name of the Space to stop.
+        ///
+        /// Defaults to the Space containing the current directory, then to
+        /// the last used Space.
+        #[arg(conflicts_with_all = ["all", "group"], add = ArgValueCandidates::new(completions::spaces))]
+        space: Option<String>,
+        /// Stops every running Space.
+        #[arg(long, conflicts_with = "group")]
+        all: bool,
+        /// Stops every Space of the group, and their combined session.
+        #[arg(long, value_name = "GROUP", add = ArgValueCandidates::new(completions::groups))]
+        group: Option<String>,
+        /// Sends `C-c` to every pane and waits for them to exit before
+        /// killing the session.
+        #[arg(long)]
+        graceful: bool,
+        /// How long to wait for the panes to exit, in seconds.
+        #[arg(long, default_value_t = 5, requires = "graceful")]
+        timeout: u64,
+    },
+    /// Stops a Space if it is running and goes to it again, with its current
+    /// tree.
+    Restart {
+        /// Name of the Space to restart.
+        ///
+        /// Defaults to the Space containing the current directory, then to
+        /// the last used Space.
+        #[arg(add = ArgValueCandidates::new(completions::spaces))]
+        space: Option<String>,
+        /// Sends `C-c` to every pane and waits for them to exit before
+        /// killing the session.
+        #[arg(long)]
+        graceful: bool,
+        /// How long to wait for the panes to exit, in seconds.
+        #[arg(long, default_value_t = 5, requires = "graceful")]
+        timeout: u64,
+    },
+    /// Prints (to stdout) the shell integration, a wrapper function going to
+    /// the Spaces and a prompt helper.
+    ///
+    /// Add `eval "$(devspace shell-init bash)"` to `~/.bashrc`, likewise for
+    /// zsh, or `devspace shell-init fish | source` to the fish config.
+    ShellInit {
+        /// The shell to integrate with.
+        #[arg(value_enum)]
+        shell: Shell,
+        /// Name of the wrapper function.
+        #[arg(long, default_value = "ds")]
+        cmd: String,
+    },
+    /// Prints (to stdout) the completion script of the shell, completing the
+    /// names of the Spaces, Trees and groups too.
+    ///
+    /// Add `source <(devspace completions bash)` to `~/.bashrc`, likewise for
+    /// zsh, or `devspace completions fish | source` to the fish config.
+    Completions {
+        /// The shell to complete in.
+        #[arg(value_enum)]
+        shell: CompletionShell,
+    },
+    /// Prints (to stdout) the man page of devspace, or of one of its
+    /// subcommands, with the syntax of the trees.
+    ///
+    /// `devspace man go | man -l -` reads the page of `go`, `--out-dir` writes
+    /// every page instead, e.g to `/usr/local/share/man/man1/`.
+    Man {
+        /// The subcommand whose page is printed, e.g `go` or `group create`.
+        #[arg(conflicts_with = "out_dir")]
+        subcmds: Vec<String>,
+        /// Writes the pages of devspace and of every subcommand to this
+        /// directory.
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+    /// Manages the groups of Spaces, launched together with `go --group`.
+    Group {
+        #[command(subcommand)]
+        cmd: GroupCommand,
     },
     /// Edit a space config.
     Edit {
-        /// Name of the Space to go to.
-        space: String,
+        /// Name of the Space to edit.
+        ///
+        /// Defaults to the Space containing the current directory, then to
+        /// the last used Space.
+        #[arg(add = ArgValueCandidates::new(completions::spaces))]
+        space: Option<String>,
         /// The new working directory of the Space.
         #[arg(long, short)]
         wdir: Option<PathBuf>,
         /// The new tree of the Space.
-        #[arg(long, short)]
+        #[arg(long, short, add = ArgValueCandidates::new(completions::trees))]
         tree: Option<SpaceTreeId>,
+        /// The new description of the Space.
+        #[arg(long)]
+        description: Option<String>,
+        /// Adds a tag to the Space, can be repeated.
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Removes a tag from the Space, can be repeated.
+        #[arg(long = "untag")]
+        untags: Vec<String>,
+        /// The hooks of the Space, an empty command removes the hook.
+        #[command(flatten)]
+        hooks: HookArgs,
+        /// Adds an env file loaded in the session of the Space, relative to
+        /// its working directory, can be repeated.
+        #[arg(long = "env-file", value_name = "FILE")]
+        env_files: Vec<PathBuf>,
+        /// Removes an env file from the Space, can be repeated.
+        #[arg(long = "rm-env-file", value_name = "FILE")]
+        rm_env_files: Vec<PathBuf>,
     },
     /// Interactive tree creation.
     ///
@@ -176,13 +518,82 @@ pub enum Command {
         name: String,
     },
     /// Removes the Tree with the given name.
+    ///
+    /// Refuses to remove a Tree still used by Spaces or as the default tree,
+    /// even with `--force` the default tree needs `--reassign`.
     #[command(visible_alias = "rm-t")]
     RemoveTree {
         /// Name of the Tree to remove.
+        #[arg(add = ArgValueCandidates::new(completions::trees))]
         name: String,
+        /// Makes the Spaces, and the default tree, using the removed Tree use
+        /// this one instead.
+        #[arg(long, add = ArgValueCandidates::new(completions::trees))]
+        reassign: Option<SpaceTreeId>,
+        /// Removes the Tree even if it is still used by Spaces.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Checks every Tree of the config without launching anything.
+    ///
+    /// Reports placeholder errors, empty commands, useless splits, unused or
+    /// unknown trees and commands not found on the `PATH`. Returns a non-zero
+    /// exit code if there are errors.
+    Check {
+        /// Also returns a non-zero exit code if there are warnings.
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Checks the Spaces for missing directories, unknown trees and shared
+    /// directories, and offers to fix them.
+    Doctor {
+        /// Only reports the issues, returns a non-zero exit code if any.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Converts the database and the config files to another format.
+    ///
+    /// The old files are kept next to the new ones, e.g `db.ron.bak`.
+    Convert {
+        /// The format to convert to.
+        #[arg(long, value_enum)]
+        to: Format,
+    },
+    /// Upgrades the database and the config files to their latest version.
+    ///
+    /// Outdated files are always migrated when loaded, a backup of the
+    /// original file is kept next to it, e.g `db.ron.v0.bak`.
+    Migrate {
+        /// Only reports what would change without writing anything.
+        #[arg(long)]
+        check: bool,
     },
 }
 
+impl Command {
+    /// Is this subcommand made to inspect or fix the Spaces and the Trees, or
+    /// doesn't launch any tree, and so must work even if they reference
+    /// unknown trees?
+    pub fn repairs(&self) -> bool {
+        matches!(
+            self,
+            Command::Init { .. }
+                | Command::Wdir { .. }
+                | Command::Current { .. }
+                | Command::ListSpaces { .. }
+                | Command::ListTrees
+                | Command::RemoveSpace { .. }
+                | Command::Edit { .. }
+                | Command::Group { .. }
+                | Command::NewTree { .. }
+                | Command::RemoveTree { .. }
+                | Command::Check { .. }
+                | Command::Doctor { .. }
+                | Command::Migrate { .. }
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Context {
     dir: PathBuf,
@@ -194,82 +605,208 @@ pub struct Context {
     db_buf: String,
     /// The Config file buffer
     conf_buf: String,
+    /// Path of the DataBase file when loaded.
+    db_path: PathBuf,
+    /// Path of the Config file when loaded.
+    conf_path: PathBuf,
+    /// Format the DataBase is written back in.
+    db_format: Format,
+    /// Format the Config is written back in.
+    conf_format: Format,
+    /// Migrations applied to the files when loaded, backups of the files are
+    /// written when terminating.
+    migrations: Vec<Migration>,
+    /// Should we discard the changes instead of writing back our files?
+    read_only: bool,
 }
 
 impl Context {
+    /// Loads the Context and validates the trees referenced by the config and
+    /// the database.
     pub fn new(dir: PathBuf) -> Result<Context> {
+        let mut ctx = Context::load(dir)?;
+        if let Err(err) = ctx.validate() {
+            // nothing changed, don't write anything back.
+            ctx.set_read_only();
+            ctx.terminate()?;
+            return Err(err);
+        }
+        Ok(ctx)
+    }
+
+    /// Loads the Context without validating it.
+    pub fn load(dir: PathBuf) -> Result<Context> {
         create_dir_all(&dir)?;
-        let db_path = Context::db_file_path(&dir);
+        let db_format = Format::detect(&dir, DB_FILE)?;
+        let db_path = db_format.file_path(&dir, DB_FILE);
         let db_buf = if db_path.exists() {
             // file exists, read it and put it in buf
-            read_to_string(Context::db_file_path(&dir))?
+            read_to_string(&db_path)?
         } else {
             // file doesn't exist put the default in the buffer
-            ron::ser::to_string_pretty(&DataBase::default(), utils::pretty_printer_config())?
+            db_format.serialize(&DataBase::default())?
         };
 
-        let conf_path = Context::conf_file_path(&dir);
+        let conf_format = Format::detect(&dir, CONF_FILE)?;
+        let conf_path = conf_format.file_path(&dir, CONF_FILE);
         let conf_buf = if conf_path.exists() {
             // file exists, read it and put it in buf
-            read_to_string(Context::conf_file_path(&dir))?
+            read_to_string(&conf_path)?
         } else {
             // file doesn't exist put the default in the buffer
-            ron::ser::to_string_pretty(&Config::default(), utils::pretty_printer_config())?
+            conf_format.serialize(&Config::default())?
         };
 
+        let (db, db_migration) = migrations::load_db(&db_path, &db_buf)?;
+        let (mut config, conf_migration) = migrations::load_config(&conf_path, &conf_buf)?;
+        config.load_includes(&dir, &conf_path)?;
+
+        // only the files that exist on disk can be migrated.
+        let migrations = [db_migration, conf_migration]
+            .into_iter()
+            .flatten()
+            .filter(|m| m.file.exists())
+            .collect();
+
         Ok(Context {
             dir,
-            db: ron::from_str(&db_buf)?,
-            config: ron::from_str(&conf_buf)?,
+            db,
+            config,
             terminated: false,
             db_buf,
             conf_buf,
+            db_path,
+            conf_path,
+            db_format,
+            conf_format,
+            migrations,
+            read_only: false,
         })
     }
 
+    /// Checks that the default tree and the trees of every Space exist.
+    pub fn validate(&self) -> Result {
+        if self.config.get_tree(&self.config.default_tree).is_err() {
+            return Err(DsError::UnknownDefaultTree(
+                self.config.default_tree.clone(),
+                self.conf_path.clone(),
+            ));
+        }
+
+        let mut spaces = self.db.spaces_iter().collect::<Vec<_>>();
+        spaces.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, space) in spaces {
+            if self.config.get_tree(&space.tree).is_err() {
+                return Err(DsError::SpaceUsesUnknownTree(
+                    name.clone(),
+                    space.tree.clone(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn terminate(&mut self) -> Result {
         self.terminated = true;
 
+        if self.read_only {
+            return Ok(());
+        }
+
+        // keep the original files before overwriting them with the migrated
+        // ones.
+        for migration in &self.migrations {
+            migration.backup()?;
+        }
+
         // write the db to the buf if we forgot to do se before.
         self.write_db_to_buf()?;
 
         // write back the database to file
-        let mut db_file = File::create(Context::db_file_path(&self.dir))?;
+        let db_path = self.db_format.file_path(&self.dir, DB_FILE);
+        let mut db_file = File::create(&db_path)?;
 
         // here we are forced to clone because we later borrow self
         db_file.write_all(&self.db_buf.clone().into_bytes())?;
+        Context::backup_converted(&self.db_path, &db_path)?;
 
         // write the conf to the buf if we forgot to do se before.
         self.write_conf_to_buf()?;
 
         // write back the config to file
-        let mut conf_file = File::create(Context::conf_file_path(&self.dir))?;
+        let conf_path = self.conf_format.file_path(&self.dir, CONF_FILE);
+        let mut conf_file = File::create(&conf_path)?;
         // cannot move things because we implement Drop to check if we terminated.
         conf_file.write_all(&self.conf_buf.clone().into_bytes())?;
+        Context::backup_converted(&self.conf_path, &conf_path)?;
 
         Ok(())
     }
 
+    /// If the file was written in another format than the one it was loaded
+    /// from, move the old file out of the way, e.g `db.ron` to `db.ron.bak`.
+    fn backup_converted(loaded: &Path, written: &Path) -> Result {
+        if loaded != written && loaded.exists() {
+            let mut backup = loaded.as_os_str().to_os_string();
+            backup.push(".bak");
+            rename(loaded, backup)?;
+        }
+        Ok(())
+    }
+
     pub(crate) fn write_db_to_buf(&mut self) -> Result {
-        self.db_buf = ron::ser::to_string_pretty(&self.db, utils::pretty_printer_config())?;
+        self.db_buf = self.db_format.serialize(&self.db)?;
         Ok(())
     }
 
     pub(crate) fn write_conf_to_buf(&mut self) -> Result {
-        self.conf_buf = ron::ser::to_string_pretty(&self.config, utils::pretty_printer_config())?;
+        self.conf_buf = self
+            .conf_format
+            .serialize(&self.config.without_included())?;
         Ok(())
     }
 
-    pub(crate) fn db_file_path(dir: impl Into<PathBuf>) -> PathBuf {
-        let mut dir = dir.into();
-        dir.push("db.ron");
-        dir
+    /// Writes back the database and the config in the given format when
+    /// terminating.
+    pub fn convert(&mut self, format: Format) {
+        self.db_format = format;
+        self.conf_format = format;
+    }
+
+    /// Paths of the database and the config as they were loaded.
+    pub fn file_paths(&self) -> (&Path, &Path) {
+        (&self.db_path, &self.conf_path)
+    }
+
+    /// Migrations applied to the files when they were loaded.
+    pub fn migrations(&self) -> &[Migration] {
+        &self.migrations
+    }
+
+    /// Discards every change instead of writing back the files when
+    /// terminating.
+    pub fn set_read_only(&mut self) {
+        self.read_only = true;
     }
 
-    pub(crate) fn conf_file_path(dir: impl Into<PathBuf>) -> PathBuf {
-        let mut dir = dir.into();
-        dir.push("config.ron");
-        dir
+    /// Resolves the name of the Space a command applies to.
+    ///
+    /// If no `space` is given, defaults to the Space containing the current
+    /// directory and then to the last used Space.
+    pub fn resolve_space(&self, space: Option<String>) -> Result<String> {
+        if let Some(space) = space {
+            return Ok(space);
+        }
+
+        if let Some((name, _)) = self.db.current_space()? {
+            return Ok(name.clone());
+        }
+
+        self.db
+            .last_space()
+            .map(String::from)
+            .ok_or(DsError::NoSpaceGiven)
     }
 
     /// Returns the session name of the given `space`
@@ -278,6 +815,33 @@ impl Context {
         sname.push_str(space);
         sname
     }
+
+    /// Returns the tmux target of the session, `=` makes tmux match its exact
+    /// name rather than a prefix, e.g `Space_foobar` for `Space_foo`.
+    pub fn session_target(session_name: &str) -> String {
+        format!("={session_name}")
+    }
+
+    /// Returns the name of the session combining the Spaces of the given
+    /// `group`, see `go --group --combined`.
+    pub fn group_session_name(&self, group: &str) -> String {
+        let mut sname = String::from("Group_");
+        sname.push_str(group);
+        sname
+    }
+
+    /// Returns the names of the sessions of the groups of the Space, when
+    /// combined its window can be in any of them, sorted.
+    pub fn combined_session_names(&self, space: &str) -> Vec<String> {
+        let mut snames = self
+            .db
+            .groups_iter()
+            .filter(|(_, members)| members.iter().any(|member| member == space))
+            .map(|(group, _)| self.group_session_name(group))
+            .collect::<Vec<_>>();
+        snames.sort();
+        snames
+    }
 }
 
 impl Drop for Context {
@@ -300,15 +864,106 @@ impl Drop for Context {
 
 pub fn run_command(args: Cli, ctx: &mut Context, repl: bool) -> Result {
     match args.subcmds {
-        Some(Command::Init { path, tree }) => init::command(ctx, path, tree)?,
-        Some(Command::Wdir { space }) => wdir::command(ctx, space)?,
-        Some(Command::ListSpaces) => list_spaces::command(ctx)?,
+        Some(Command::Init {
+            path,
+            tree,
+            description,
+            tags,
+        }) => init::command(ctx, path, tree, description, tags)?,
+        Some(Command::Wdir { space }) => {
+            let space = ctx.resolve_space(space)?;
+            wdir::command(ctx, space)?
+        }
+        Some(Command::ListSpaces { tags, sort }) => list_spaces::command(ctx, tags, sort)?,
+        Some(Command::Current { wdir }) => current::command(ctx, wdir)?,
         Some(Command::ListTrees) => list_trees::command(ctx)?,
-        Some(Command::RemoveSpace { space }) => remove_space::command(ctx, space)?,
-        Some(Command::Go { space }) => go::command(ctx, space)?,
-        Some(Command::Edit { space, wdir, tree }) => edit::command(ctx, space, wdir, tree)?,
+        Some(Command::RemoveSpace { space }) => {
+            let guessed = space.is_none();
+            let space = ctx.resolve_space(space)?;
+            remove_space::command(ctx, space, guessed)?
+        }
+        Some(Command::Go {
+            group: Some(group),
+            combined,
+            ..
+        }) => go::group(ctx, group, combined)?,
+        Some(Command::Go {
+            space,
+            dry_run,
+            new_window,
+            reload,
+            ..
+        }) => {
+            let space = ctx.resolve_space(space)?;
+            go::command(ctx, space, dry_run, new_window, reload)?
+        }
+        Some(Command::ExportScript { space, format }) => {
+            let space = ctx.resolve_space(space)?;
+            export_script::command(ctx, space, format)?
+        }
+        Some(Command::Import { files, space }) => import::command(ctx, files, space)?,
+        Some(Command::Capture { session, tree }) => capture::command(ctx, session, tree)?,
+        Some(Command::Status { running }) => status::command(ctx, running)?,
+        Some(Command::Stop {
+            all: true,
+            graceful,
+            timeout,
+            ..
+        }) => stop::all(ctx, graceful, timeout)?,
+        Some(Command::Stop {
+            group: Some(group),
+            graceful,
+            timeout,
+            ..
+        }) => stop::group(ctx, group, graceful, timeout)?,
+        Some(Command::Stop {
+            space,
+            graceful,
+            timeout,
+            ..
+        }) => {
+            let space = ctx.resolve_space(space)?;
+            stop::command(ctx, space, graceful, timeout)?
+        }
+        Some(Command::Restart {
+            space,
+            graceful,
+            timeout,
+        }) => {
+            let space = ctx.resolve_space(space)?;
+            stop::restart(ctx, space, graceful, timeout)?
+        }
+        Some(Command::Edit {
+            space,
+            wdir,
+            tree,
+            description,
+            tags,
+            untags,
+            hooks,
+            env_files,
+            rm_env_files,
+        }) => {
+            let space = ctx.resolve_space(space)?;
+            edit::command(ctx, space.clone(), wdir, tree, description, tags, untags)?;
+            edit::hooks(ctx, &space, hooks.into())?;
+            edit::env_files(ctx, &space, env_files, rm_env_files)?
+        }
+        // usually run by `run_without_context`, they ignore the Context.
+        Some(Command::ShellInit { shell, cmd }) => shell_init::command(shell, &cmd)?,
+        Some(Command::Completions { shell }) => completions::command(shell)?,
+        Some(Command::Man { subcmds, out_dir }) => man::command(subcmds, out_dir.as_deref())?,
+        Some(Command::Group { cmd }) => group::command(ctx, cmd)?,
         Some(Command::NewTree { name }) => new_tree::command(ctx, name)?,
-        Some(Command::RemoveTree { name }) => remove_tree::command(ctx, name)?,
+        Some(Command::RemoveTree {
+            name,
+            reassign,
+            force,
+        }) => remove_tree::command(ctx, name, reassign, force)?,
+        Some(Command::Check { strict }) => check::command(ctx, strict)?,
+        Some(Command::Doctor { check }) => doctor::command(ctx, check)?,
+        Some(Command::Convert { to }) => convert::command(ctx, to)?,
+        Some(Command::Migrate { check }) => migrate::command(ctx, check)?,
         None if !repl => {
             repl::run()?;
         }
@@ -317,13 +972,36 @@ pub fn run_command(args: Cli, ctx: &mut Context, repl: bool) -> Result {
     Ok(())
 }
 
+/// Runs the subcommands that don't use the Context, without loading it so
+/// they neither touch nor depend on the files.
+///
+/// Returns `None` if the subcommand needs the Context.
+pub fn run_without_context(args: &Cli) -> Option<Result> {
+    match &args.subcmds {
+        Some(Command::ShellInit { shell, cmd }) => Some(shell_init::command(*shell, cmd)),
+        Some(Command::Completions { shell }) => Some(completions::command(*shell)),
+        Some(Command::Man { subcmds, out_dir }) => {
+            Some(man::command(subcmds.clone(), out_dir.as_deref()))
+        }
+        _ => None,
+    }
+}
+
 pub fn run() -> Result {
+    // when called back by the shell to complete, completes and exits.
+    CompleteEnv::with_factory(Cli::command)
+        .var(COMPLETE_VAR)
+        .complete();
+
     let matches = Cli::command()
         .version(build::CLAP_LONG_VERSION)
         .get_matches();
     let args = Cli::from_arg_matches(&matches)?;
+    if let Some(res) = run_without_context(&args) {
+        return res;
+    }
 
-    let mut ctx = Context::new(args.dir()?)?;
+    let mut ctx = args.context()?;
 
     run_command(args, &mut ctx, false)?;
 
@@ -0,0 +1,326 @@
+//! Versions of the on-disk layout of the database and the config, and the
+//! migrations upgrading older files to the current layout.
+//!
+//! Every file stores the `version` of its layout. When a file older than the
+//! current version is loaded, the migration steps are applied one after the
+//! other, each step reading the layout of its version and writing the layout
+//! of the next one.
+//!
+//! A field that can be defaulted when missing doesn't need a new version.
+
+use std::{
+    collections::HashMap,
+    fs::copy,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    DsError, Result,
+    config::{Config, SpaceTree, SpaceTreeId},
+    database::DataBase,
+    diagnostics::ParseError,
+    format::Format,
+};
+
+/// Current version of the layout of `db.ron`.
+pub const DB_VERSION: u32 = 1;
+
+/// Current version of the layout of `config.ron`.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// A step upgrading a file from the version `from` to the version `from + 1`.
+struct MigrationStep {
+    from: u32,
+    /// What is changed in the file by this step.
+    description: &'static str,
+    migrate: fn(Format, &str) -> Result<String>,
+}
+
+const DB_STEPS: &[MigrationStep] = &[MigrationStep {
+    from: 0,
+    description: "add the `version` field.",
+    migrate: v0::migrate_db,
+}];
+
+const CONFIG_STEPS: &[MigrationStep] = &[MigrationStep {
+    from: 0,
+    description: "add the `version` field.",
+    migrate: v0::migrate_config,
+}];
+
+/// A migration applied (or to apply) to a file.
+#[derive(Debug, Clone)]
+pub struct Migration {
+    /// Path of the migrated file.
+    pub file: PathBuf,
+    /// Version of the file before the migration.
+    pub from: u32,
+    /// Version of the file after the migration.
+    pub to: u32,
+    /// Description of every step of the migration.
+    pub changes: Vec<&'static str>,
+}
+
+impl Migration {
+    /// Path of the backup of the file before it is migrated, e.g
+    /// `db.ron.v0.bak`
+    pub fn backup_path(&self) -> PathBuf {
+        let mut name = self.file.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".v{}.bak", self.from));
+        self.file.with_file_name(name)
+    }
+
+    /// Copies the file, as it is before the migration, to its backup path.
+    pub fn backup(&self) -> Result {
+        copy(&self.file, self.backup_path())?;
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "DataBase")]
+struct DataBaseVersion {
+    #[serde(default)]
+    version: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Config")]
+struct ConfigVersion {
+    #[serde(default)]
+    version: u32,
+}
+
+/// Loads the database from its buffer, migrating it if it is outdated.
+///
+/// Returns the migration applied to the buffer, if any.
+pub fn load_db(path: &Path, buf: &str) -> Result<(DataBase, Option<Migration>)> {
+    let format = Format::from_path(path).unwrap_or_default();
+    let version = format.parse_file::<DataBaseVersion>(path, buf)?.version;
+    load(path, format, buf, version, DB_VERSION, DB_STEPS)
+}
+
+/// Loads the config from its buffer, migrating it if it is outdated.
+///
+/// Returns the migration applied to the buffer, if any.
+pub fn load_config(path: &Path, buf: &str) -> Result<(Config, Option<Migration>)> {
+    let format = Format::from_path(path).unwrap_or_default();
+    let version = format.parse_file::<ConfigVersion>(path, buf)?.version;
+    load(path, format, buf, version, CONFIG_VERSION, CONFIG_STEPS)
+}
+
+fn load<T>(
+    path: &Path,
+    format: Format,
+    buf: &str,
+    version: u32,
+    current: u32,
+    steps: &[MigrationStep],
+) -> Result<(T, Option<Migration>)>
+where
+    T: DeserializeOwned,
+{
+    if version > current {
+        return Err(DsError::UnsupportedVersion {
+            file: path.to_path_buf(),
+            version,
+            supported: current,
+        });
+    }
+
+    if version == current {
+        return Ok((format.parse_file(path, buf)?, None));
+    }
+
+    let mut migrated = buf.to_string();
+    let mut changes = Vec::new();
+    for step in steps.iter().filter(|step| step.from >= version) {
+        migrated = (step.migrate)(format, &migrated)
+            .map_err(|err| ParseError::from_error(path, buf, err))?;
+        changes.push(step.description);
+    }
+
+    let migration = Migration {
+        file: path.to_path_buf(),
+        from: version,
+        to: current,
+        changes,
+    };
+
+    Ok((format.parse(&migrated)?, Some(migration)))
+}
+
+/// Layout before the files were versioned.
+mod v0 {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(rename = "DataBase")]
+    struct DataBase {
+        entries: HashMap<String, Space>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename = "Space")]
+    struct Space {
+        wdir: PathBuf,
+        tree: SpaceTreeId,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename = "Config")]
+    struct Config {
+        default_tree: SpaceTreeId,
+        trees: HashMap<SpaceTreeId, SpaceTree>,
+    }
+
+    pub(super) fn migrate_db(format: Format, buf: &str) -> Result<String> {
+        let old: DataBase = format.parse(buf)?;
+
+        let db = v1::DataBase {
+            version: 1,
+            entries: old
+                .entries
+                .into_iter()
+                .map(|(name, space)| {
+                    let space = v1::Space {
+                        wdir: space.wdir,
+                        tree: space.tree,
+                    };
+                    (name, space)
+                })
+                .collect(),
+        };
+
+        format.serialize(&db)
+    }
+
+    pub(super) fn migrate_config(format: Format, buf: &str) -> Result<String> {
+        let old: Config = format.parse(buf)?;
+
+        let config = v1::Config {
+            version: 1,
+            default_tree: old.default_tree,
+            trees: old.trees,
+        };
+
+        format.serialize(&config)
+    }
+}
+
+/// Layout of the version 1, the output of the steps from the version 0.
+///
+/// Frozen, the fields added since with a default are left out so the next
+/// steps always read this exact layout.
+mod v1 {
+    use super::*;
+
+    #[derive(Deserialize, Serialize)]
+    #[serde(rename = "Config")]
+    pub(super) struct Config {
+        pub(super) version: u32,
+        pub(super) default_tree: SpaceTreeId,
+        pub(super) trees: HashMap<SpaceTreeId, SpaceTree>,
+    }
+
+    #[derive(Deserialize, Serialize)]
+    #[serde(rename = "DataBase")]
+    pub(super) struct DataBase {
+        pub(super) version: u32,
+        pub(super) entries: HashMap<String, Space>,
+    }
+
+    #[derive(Deserialize, Serialize)]
+    #[serde(rename = "Space")]
+    pub(super) struct Space {
+        pub(super) wdir: PathBuf,
+        pub(super) tree: SpaceTreeId,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V0_DB: &str = r#"(
+    entries: {
+        "blog": (wdir: "/srv/blog", tree: "ide"),
+    },
+)"#;
+
+    const V0_CONFIG: &str = r#"(
+    default_tree: "ide",
+    trees: {
+        "ide": TmuxVSplit(lhs: Some(Cmd("hx")), rhs: None),
+    },
+)"#;
+
+    #[test]
+    fn db_from_v0() {
+        let (db, migration) = load_db(Path::new("db.ron"), V0_DB).unwrap();
+
+        assert_eq!(db.version, DB_VERSION);
+        let space = db.get_space("blog").unwrap();
+        assert_eq!(space.wdir, PathBuf::from("/srv/blog"));
+        assert_eq!(space.tree, SpaceTreeId::from("ide"));
+        assert_eq!(space.created_at, None);
+        assert_eq!(space.launch_count, 0);
+
+        let migration = migration.expect("migrated");
+        assert_eq!((migration.from, migration.to), (0, DB_VERSION));
+        assert_eq!(migration.changes.len(), DB_STEPS.len());
+        assert_eq!(migration.backup_path(), PathBuf::from("db.ron.v0.bak"));
+    }
+
+    #[test]
+    fn config_from_v0() {
+        let (config, migration) = load_config(Path::new("config.ron"), V0_CONFIG).unwrap();
+
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.default_tree, SpaceTreeId::from("ide"));
+        assert_eq!(
+            config.get_tree(&"ide".into()).unwrap(),
+            &SpaceTree::TmuxVSplit {
+                lhs: Some(Box::new(SpaceTree::Cmd(String::from("hx")))),
+                rhs: None,
+                size: None,
+            }
+        );
+        assert!(config.include.is_empty());
+        assert!(config.hooks.is_empty());
+
+        let migration = migration.expect("migrated");
+        assert_eq!((migration.from, migration.to), (0, CONFIG_VERSION));
+    }
+
+    #[test]
+    fn json_from_v0() {
+        let buf = r#"{"entries": {"blog": {"wdir": "/srv/blog", "tree": "ide"}}}"#;
+        let (db, migration) = load_db(Path::new("db.json"), buf).unwrap();
+
+        assert!(migration.is_some());
+        assert_eq!(db.get_space("blog").unwrap().tree, SpaceTreeId::from("ide"));
+    }
+
+    #[test]
+    fn current_files_are_untouched() {
+        let buf = Format::Ron.serialize(&DataBase::default()).unwrap();
+        let (_, migration) = load_db(Path::new("db.ron"), &buf).unwrap();
+        assert!(migration.is_none());
+
+        let buf = Format::Ron.serialize(&Config::default()).unwrap();
+        let (_, migration) = load_config(Path::new("config.ron"), &buf).unwrap();
+        assert!(migration.is_none());
+    }
+
+    #[test]
+    fn newer_files_are_refused() {
+        let buf = format!("(version: {}, entries: {{}})", DB_VERSION + 1);
+        let err = load_db(Path::new("db.ron"), &buf).unwrap_err();
+        assert!(
+            matches!(err, DsError::UnsupportedVersion { version, .. } if version == DB_VERSION + 1)
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Per-project configuration, a `.devspace.ron` file at the root of a
+//! project, usually checked into its repository.
+//!
+//! The tree of the project file is used instead of the tree of the Space. It
+//! runs commands written by someone else, so it must be trusted before being
+//! used and trusted again every time it changes.
+
+use std::{
+    fs::read_to_string,
+    io::{Write, stdin, stdout},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Result, config::SpaceTree, database::DataBase, format::Format, new_tree::yes_or_no};
+
+/// Name of the project file.
+pub const PROJECT_FILE: &str = ".devspace.ron";
+
+/// The content of a project file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename = "Project")]
+pub struct ProjectConfig {
+    /// The tree used to launch the project.
+    pub tree: SpaceTree,
+}
+
+/// Path of the project file of the given working directory.
+pub fn project_file_path(wdir: &Path) -> PathBuf {
+    wdir.join(PROJECT_FILE)
+}
+
+/// Reads the project file of the working directory, if there is one.
+///
+/// Returns the content of the file along with the parsed config.
+pub fn load(wdir: &Path) -> Result<Option<(String, ProjectConfig)>> {
+    let path = project_file_path(wdir);
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let buf = read_to_string(&path)?;
+    let project = Format::Ron.parse_file(&path, &buf)?;
+    Ok(Some((buf, project)))
+}
+
+/// Returns the tree of the project file of the working directory, if there is
+/// one and it is already trusted, without asking anything.
+///
+/// An untrusted project file is reported on stderr, to keep stdout clean for
+/// the scripts printed.
+pub fn known_trusted_tree(db: &DataBase, wdir: &Path) -> Result<Option<SpaceTree>> {
+    let Some((buf, project)) = load(wdir)? else {
+        return Ok(None);
+    };
+    let path = project_file_path(wdir);
+
+    if db.is_trusted(&path, &buf) {
+        return Ok(Some(project.tree));
+    }
+
+    eprintln!(
+        "The project file {} isn't trusted, the tree of the Space is used instead.",
+        path.display()
+    );
+    Ok(None)
+}
+
+/// Returns the tree of the project file of the working directory, if there is
+/// one and it is trusted.
+///
+/// If the file was never trusted or changed since, asks if it should be
+/// trusted and remembers the answer in the database.
+pub fn trusted_tree(db: &mut DataBase, wdir: &Path) -> Result<Option<SpaceTree>> {
+    let Some((buf, project)) = load(wdir)? else {
+        return Ok(None);
+    };
+    let path = project_file_path(wdir);
+
+    if db.is_trusted(&path, &buf) {
+        return Ok(Some(project.tree));
+    }
+
+    let mut stdout = stdout();
+    if db.is_known_project(&path) {
+        writeln!(stdout, "The project file {} changed.", path.display())?;
+    } else {
+        writeln!(stdout, "Found the project file {}.", path.display())?;
+    }
+    writeln!(stdout, "Its tree will run the following:\n")?;
+    project.tree.pretty_print(&mut stdout, 0)?;
+    writeln!(stdout)?;
+
+    write!(stdout, "Trust it? ")?;
+    stdout.flush()?;
+    if yes_or_no(&mut stdout, &stdin(), false)? {
+        db.trust(path, &buf);
+        Ok(Some(project.tree))
+    } else {
+        writeln!(
+            stdout,
+            "Not trusted, the tree of the Space is used instead."
+        )?;
+        Ok(None)
+    }
+}
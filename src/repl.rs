@@ -2,7 +2,7 @@ use std::io::{Stdout, Write, stdin, stdout};
 
 use clap::{Command, CommandFactory, FromArgMatches};
 
-use crate::{Cli, Context, DsError, Result, run_command};
+use crate::{Cli, DsError, Result, run_command, run_without_context};
 
 pub fn run() -> Result {
     println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
@@ -51,14 +51,14 @@ pub fn respond(cli: Command, cmd: &str) -> Result<bool> {
     let args = shlex::split(cmd).ok_or(DsError::InvalidREPL)?;
     let matches = cli.try_get_matches_from(args)?;
 
-    match matches.subcommand() {
-        Some(("quit", _)) => {
-            return Ok(true);
-        }
-        _ => {}
+    if let Some(("quit", _)) = matches.subcommand() {
+        return Ok(true);
     }
     let args = Cli::from_arg_matches(&matches)?;
-    let mut ctx = Context::new(args.dir()?)?;
+    if let Some(res) = run_without_context(&args) {
+        return res.map(|()| false);
+    }
+    let mut ctx = args.context()?;
 
     run_command(args, &mut ctx, true)?;
 
@@ -1,10 +1,14 @@
 use std::{
+    env::var,
     fs::File,
     io::{Read, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use ron::ser::PrettyConfig;
 use serde::{Serialize, de::DeserializeOwned};
+use sha2::{Digest, Sha256};
 
 use crate::Result;
 
@@ -41,3 +45,137 @@ where
     file.write_all(buf)?;
     Ok(())
 }
+
+/// Returns the current time in seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Formats the time elapsed since `timestamp`, in seconds since the Unix
+/// epoch, like `5min ago` or `3d ago`.
+pub fn format_ago(timestamp: u64) -> String {
+    match format_duration(now().saturating_sub(timestamp)) {
+        Some(duration) => format!("{duration} ago"),
+        None => String::from("just now"),
+    }
+}
+
+/// Formats a duration in seconds with its largest unit, like `5min` or `3d`,
+/// `None` if it is less than a minute.
+pub fn format_duration(secs: u64) -> Option<String> {
+    const UNITS: [(u64, &str); 5] = [
+        (60 * 60 * 24 * 365, "y"),
+        (60 * 60 * 24 * 30, "mo"),
+        (60 * 60 * 24, "d"),
+        (60 * 60, "h"),
+        (60, "min"),
+    ];
+
+    UNITS
+        .into_iter()
+        .find(|(unit_secs, _)| secs >= *unit_secs)
+        .map(|(unit_secs, unit)| format!("{}{unit}", secs / unit_secs))
+}
+
+/// The SHA-256 of the content, in hexadecimal.
+pub fn sha256(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Shells, a pane running one of them runs no command.
+const SHELLS: &[&str] = &[
+    "bash", "dash", "elvish", "fish", "ksh", "nu", "sh", "tcsh", "xonsh", "zsh",
+];
+
+/// Is the command, as shown by `#{pane_current_command}`, a shell?
+pub fn is_shell(command: &str) -> bool {
+    let shell = var("SHELL").ok();
+    let shell = shell
+        .as_deref()
+        .and_then(|shell| Path::new(shell).file_name()?.to_str());
+    SHELLS.contains(&command) || shell == Some(command)
+}
+
+/// Returns the candidate closest to `word`, if it is close enough to be a typo.
+pub fn closest<'a>(word: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let max_distance = (word.chars().count() / 3).max(1);
+    candidates
+        .iter()
+        .map(|c| (edit_distance(word, c), *c))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, c)| c)
+}
+
+/// The number of single characters edits, or swaps of two adjacent
+/// characters, to go from `a` to `b` (optimal string alignment distance).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    // the rows of the two previous characters of `a`.
+    let mut before = Vec::new();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.iter().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != cb);
+            let mut distance = substitution.min(prev[j + 1] + 1).min(row[j] + 1);
+            if i > 0 && j > 0 && *ca == b[j - 1] && a[i - 1] == *cb {
+                distance = distance.min(before[j - 1] + 1);
+            }
+            row.push(distance);
+        }
+        before = std::mem::replace(&mut prev, row);
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_hex() {
+        assert_eq!(
+            sha256("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn edit_distance_of_words() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("Cmd", "Cmd"), 0);
+        assert_eq!(edit_distance("Cmdd", "Cmd"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("héllo", "hello"), 1);
+        // a swap of two adjacent characters is a single edit.
+        assert_eq!(edit_distance("lsh", "lhs"), 1);
+        assert_eq!(edit_distance("ab", "ba"), 1);
+        assert_eq!(edit_distance("ca", "abc"), 3);
+    }
+
+    #[test]
+    fn closest_candidate() {
+        let variants = ["Cmd", "TmuxVSplit", "TmuxHSplit"];
+        assert_eq!(closest("Cmdd", &variants), Some("Cmd"));
+        assert_eq!(closest("TmuxVSlpit", &variants), Some("TmuxVSplit"));
+        assert_eq!(closest("cmd", &variants), Some("Cmd"));
+        // the short fields of the splits, transposed.
+        let fields = ["lhs", "rhs", "size"];
+        assert_eq!(closest("lsh", &fields), Some("lhs"));
+        assert_eq!(closest("szie", &fields), Some("size"));
+        // too far from every candidate to be a typo.
+        assert_eq!(closest("Window", &variants), None);
+        assert_eq!(closest("Cmdd", &[]), None);
+    }
+}